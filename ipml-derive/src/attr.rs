@@ -0,0 +1,93 @@
+use syn::ext::IdentExt;
+use syn::{Attribute, Field, Ident, LitStr, Path, Result};
+
+// Options of a struct or enum, from #[ipml(...)] on it
+#[derive(Default)]
+pub struct Container {
+    // A function checking the whole value once read, fn(&Self) -> Result<(), impl Display>
+    pub validate: Option<Path>,
+}
+
+// Options of a field, from #[ipml(...)] on it
+pub struct FieldAttr {
+    // The name of its entry in the scope
+    pub key: String,
+    // Taken when the scope has no entry, Default::default when no function is given
+    pub default: Option<Option<Path>>,
+    // Read from and written to the same scope as the struct holding it
+    pub flatten: bool,
+    pub validate: Option<Path>,
+}
+
+fn path(meta: &syn::meta::ParseNestedMeta) -> Result<Path> {
+    meta.value()?.parse::<LitStr>()?.parse()
+}
+
+fn ipml_attrs(attrs: &[Attribute]) -> impl Iterator<Item = &Attribute> {
+    attrs.iter().filter(|attr| attr.path().is_ident("ipml"))
+}
+
+impl Container {
+    pub fn parse(attrs: &[Attribute]) -> Result<Self> {
+        let mut container = Container::default();
+        for attr in ipml_attrs(attrs) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("validate") {
+                    container.validate = Some(path(&meta)?);
+                    Ok(())
+                } else {
+                    Err(meta.error("expected validate"))
+                }
+            })?;
+        }
+        Ok(container)
+    }
+}
+
+impl FieldAttr {
+    pub fn parse(field: &Field) -> Result<Self> {
+        let mut attr = FieldAttr {
+            key: field.ident.as_ref().unwrap().unraw().to_string(),
+            default: None,
+            flatten: false,
+            validate: None,
+        };
+        for a in ipml_attrs(&field.attrs) {
+            a.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    attr.key = meta.value()?.parse::<LitStr>()?.value();
+                } else if meta.path.is_ident("default") {
+                    attr.default = Some(if meta.input.peek(syn::Token![=]) {
+                        Some(path(&meta)?)
+                    } else {
+                        None
+                    });
+                } else if meta.path.is_ident("flatten") {
+                    attr.flatten = true;
+                } else if meta.path.is_ident("validate") {
+                    attr.validate = Some(path(&meta)?);
+                } else {
+                    return Err(meta.error("expected rename, default, flatten or validate"));
+                }
+                Ok(())
+            })?;
+        }
+        Ok(attr)
+    }
+}
+
+// The name of a variant, which #[ipml(rename = "...")] may change
+pub fn variant_name(ident: &Ident, attrs: &[Attribute]) -> Result<String> {
+    let mut name = ident.unraw().to_string();
+    for attr in ipml_attrs(attrs) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                name = meta.value()?.parse::<LitStr>()?.value();
+                Ok(())
+            } else {
+                Err(meta.error("expected rename"))
+            }
+        })?;
+    }
+    Ok(name)
+}
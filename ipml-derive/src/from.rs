@@ -0,0 +1,168 @@
+use crate::attr::{variant_name, Container, FieldAttr};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{Data, DeriveInput, Fields, FieldsNamed, Result};
+
+// Builds the value of a struct or struct variant from the scope in `scope`
+fn read_fields(ctor: TokenStream, fields: &FieldsNamed) -> Result<TokenStream> {
+    let mut inits = Vec::new();
+    let mut checks = Vec::new();
+    let mut idents = Vec::new();
+    for field in &fields.named {
+        let attr = FieldAttr::parse(field)?;
+        let ident = field.ident.as_ref().unwrap();
+        // Prefixed so a field cannot shadow the scope being read
+        let local = format_ident!("__{}", ident);
+        let ty = &field.ty;
+        let key = &attr.key;
+        let init = if attr.flatten {
+            quote!(<#ty as ::ipml::FromScope>::from_scope(scope)?)
+        } else {
+            match &attr.default {
+                Some(Some(default)) => {
+                    quote!(::ipml::__private::read_or(scope, #key, #default)?)
+                }
+                Some(None) => quote!(
+                    ::ipml::__private::read_or(scope, #key, ::std::default::Default::default)?
+                ),
+                None => quote!(::ipml::__private::read::<#ty>(scope, #key)?),
+            }
+        };
+        inits.push(quote!(let #local = #init;));
+        if let Some(validate) = &attr.validate {
+            checks.push(quote!(
+                ::ipml::__private::validate(::std::option::Option::Some(#key), &#local, #validate)?;
+            ));
+        }
+        idents.push(quote!(#ident: #local));
+    }
+    Ok(quote!({
+        #(#inits)*
+        #(#checks)*
+        #ctor { #(#idents),* }
+    }))
+}
+
+// Builds a variant from its name in `name` and its fields in `fields`
+fn read_variant(data: &syn::DataEnum) -> Result<TokenStream> {
+    let mut arms = Vec::new();
+    let mut names = Vec::new();
+    for variant in &data.variants {
+        let ident = &variant.ident;
+        let name = variant_name(ident, &variant.attrs)?;
+        let read = match &variant.fields {
+            Fields::Unit => quote!({
+                let _ = fields;
+                ::std::result::Result::Ok(Self::#ident)
+            }),
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                let ty = &fields.unnamed[0].ty;
+                quote!({
+                    let value = ::ipml::__private::fields(&name, fields)?;
+                    <#ty as ::ipml::FromValue>::from_value(&value)
+                        .map(Self::#ident)
+                        .map_err(|e| e.within(&name))
+                })
+            }
+            Fields::Unnamed(fields) => {
+                let len = fields.unnamed.len();
+                let items = fields.unnamed.iter().enumerate().map(|(i, field)| {
+                    let ty = &field.ty;
+                    let index = i.to_string();
+                    quote!(<#ty as ::ipml::FromValue>::from_value(&items[#i])
+                        .map_err(|e| e.within(#index))?)
+                });
+                quote!({
+                    let value = ::ipml::__private::fields(&name, fields)?;
+                    ::ipml::__private::tuple(&value, #len)
+                        .and_then(|items| ::std::result::Result::Ok(Self::#ident(#(#items),*)))
+                        .map_err(|e| e.within(&name))
+                })
+            }
+            Fields::Named(fields) => {
+                let build = read_fields(quote!(Self::#ident), fields)?;
+                quote!({
+                    let value = ::ipml::__private::fields(&name, fields)?;
+                    ::ipml::__private::with_scope(&value, |scope| {
+                        ::std::result::Result::Ok(#build)
+                    })
+                    .map_err(|e| e.within(&name))
+                })
+            }
+        };
+        arms.push(quote!(#name => #read));
+        names.push(name);
+    }
+    Ok(quote!(match name.as_str() {
+        #(#arms,)*
+        name => ::std::result::Result::Err(
+            ::ipml::__private::unknown_variant(name, &[#(#names),*])
+        ),
+    }))
+}
+
+pub fn derive(input: &DeriveInput) -> Result<TokenStream> {
+    let container = Container::parse(&input.attrs)?;
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let validate = container.validate.iter();
+
+    let (from_scope, from_value) = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => {
+                let build = read_fields(quote!(Self), fields)?;
+                (
+                    quote!(let value = #build;),
+                    quote!(::ipml::__private::with_scope(
+                        value,
+                        <Self as ::ipml::FromScope>::from_scope
+                    )),
+                )
+            }
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    ident,
+                    "FromScope can only be derived for structs with named fields and enums",
+                ))
+            }
+        },
+        Data::Enum(data) => {
+            let read = read_variant(data)?;
+            (
+                quote!(
+                    let (name, fields) = ::ipml::__private::scope_variant(scope)?;
+                    let value: Self = #read?;
+                ),
+                quote!({
+                    let (name, fields) = ::ipml::__private::variant(value)?;
+                    let value: Self = #read?;
+                    #(::ipml::__private::validate(::std::option::Option::None, &value, #validate)?;)*
+                    ::std::result::Result::Ok(value)
+                }),
+            )
+        }
+        Data::Union(_) => {
+            return Err(syn::Error::new_spanned(
+                ident,
+                "FromScope cannot be derived for unions",
+            ))
+        }
+    };
+    let validate = container.validate.iter();
+
+    Ok(quote! {
+        impl #impl_generics ::ipml::FromScope for #ident #ty_generics #where_clause {
+            fn from_scope(scope: &mut ::ipml::Scope) -> ::std::result::Result<Self, ::ipml::RuntimeError> {
+                #from_scope
+                #(::ipml::__private::validate(::std::option::Option::None, &value, #validate)?;)*
+                ::std::result::Result::Ok(value)
+            }
+        }
+
+        impl #impl_generics ::ipml::FromValue for #ident #ty_generics #where_clause {
+            fn from_value(value: &::ipml::Value) -> ::std::result::Result<Self, ::ipml::RuntimeError> {
+                #from_value
+            }
+        }
+    })
+}
@@ -0,0 +1,131 @@
+use crate::attr::{variant_name, FieldAttr};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{Data, DeriveInput, Fields, FieldsNamed, Result};
+
+// Writes the fields, bound by reference to locals of the same names, into the scope in `scope`
+fn write_fields(fields: &FieldsNamed) -> Result<TokenStream> {
+    let mut writes = Vec::new();
+    for field in &fields.named {
+        let attr = FieldAttr::parse(field)?;
+        let local = format_ident!("__{}", field.ident.as_ref().unwrap());
+        let key = &attr.key;
+        writes.push(if attr.flatten {
+            quote!(::ipml::IntoScope::set_into(#local, scope)?;)
+        } else {
+            quote!(::ipml::__private::write(scope, #key, #local)?;)
+        });
+    }
+    Ok(quote!(#(#writes)*))
+}
+
+// Binds the fields of a struct or variant to locals, so they may be written
+fn bind(fields: &FieldsNamed) -> TokenStream {
+    let binds = fields.named.iter().map(|field| {
+        let ident = field.ident.as_ref().unwrap();
+        let local = format_ident!("__{}", ident);
+        quote!(#ident: #local)
+    });
+    quote!({ #(#binds),* })
+}
+
+pub fn derive(input: &DeriveInput) -> Result<TokenStream> {
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let (set_into, to_value) = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => {
+                let bind = bind(fields);
+                let writes = write_fields(fields)?;
+                (
+                    quote!(
+                        let Self #bind = self;
+                        #writes
+                        ::std::result::Result::Ok(())
+                    ),
+                    quote!(::ipml::__private::to_scope_value(self)),
+                )
+            }
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    ident,
+                    "IntoScope can only be derived for structs with named fields and enums",
+                ))
+            }
+        },
+        // A variant is written as its name alone, or a map of its name to its fields
+        Data::Enum(data) => {
+            let mut arms = Vec::new();
+            for variant in &data.variants {
+                let var = &variant.ident;
+                let name = variant_name(var, &variant.attrs)?;
+                arms.push(match &variant.fields {
+                    Fields::Unit => quote!(
+                        Self::#var => ::ipml::Value::Str(#name.to_string())
+                    ),
+                    Fields::Unnamed(fields) => {
+                        let locals: Vec<_> = (0..fields.unnamed.len())
+                            .map(|i| format_ident!("__{}", i))
+                            .collect();
+                        let value = if locals.len() == 1 {
+                            quote!(::ipml::IntoValue::to_value(__0)?)
+                        } else {
+                            quote!(::ipml::Value::List(
+                                [#(::ipml::IntoValue::to_value(#locals)?),*]
+                                    .into_iter()
+                                    .map(::ipml::rc_cell)
+                                    .collect()
+                            ))
+                        };
+                        quote!(
+                            Self::#var(#(#locals),*) =>
+                                ::ipml::__private::variant_value(#name, #value)
+                        )
+                    }
+                    Fields::Named(fields) => {
+                        let bind = bind(fields);
+                        let writes = write_fields(fields)?;
+                        quote!(Self::#var #bind => {
+                            let fields = ::ipml::rc_cell(::ipml::Scope::new());
+                            {
+                                let scope = &mut *fields.borrow_mut();
+                                #writes
+                            }
+                            ::ipml::__private::variant_value(#name, ::ipml::Value::Scope(fields))
+                        })
+                    }
+                });
+            }
+            (
+                quote!(::ipml::__private::set_variant(
+                    scope,
+                    ::ipml::IntoValue::to_value(self)?
+                )),
+                quote!(::std::result::Result::Ok(match self {
+                    #(#arms,)*
+                })),
+            )
+        }
+        Data::Union(_) => {
+            return Err(syn::Error::new_spanned(
+                ident,
+                "IntoScope cannot be derived for unions",
+            ))
+        }
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::ipml::IntoScope for #ident #ty_generics #where_clause {
+            fn set_into(&self, scope: &mut ::ipml::Scope) -> ::std::result::Result<(), ::ipml::RuntimeError> {
+                #set_into
+            }
+        }
+
+        impl #impl_generics ::ipml::IntoValue for #ident #ty_generics #where_clause {
+            fn to_value(&self) -> ::std::result::Result<::ipml::Value, ::ipml::RuntimeError> {
+                #to_value
+            }
+        }
+    })
+}
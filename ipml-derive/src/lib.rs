@@ -0,0 +1,37 @@
+//! Derive macros for reading structs and enums from IPML scopes and writing them back
+//!
+//! Use them through the `derive` feature of `ipml`, which re-exports them next to the
+//! `FromScope` and `IntoScope` traits they implement.
+
+mod attr;
+mod from;
+mod into;
+
+use proc_macro::TokenStream;
+use syn::{parse_macro_input, DeriveInput};
+
+/// Implements `FromScope` and `FromValue`, reading each field from the entry of its name
+///
+/// Fields take `#[ipml(rename = "key")]`, `#[ipml(default)]` or `#[ipml(default = "path")]`
+/// for a missing entry, `#[ipml(flatten)]` to read a struct from the same scope, and
+/// `#[ipml(validate = "path")]` to check the value read. The struct or enum itself may take
+/// `#[ipml(validate = "path")]` as well, and variants take `#[ipml(rename = "name")]`.
+#[proc_macro_derive(FromScope, attributes(ipml))]
+pub fn derive_from_scope(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    from::derive(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Implements `IntoScope` and `IntoValue`, writing each field as the entry of its name
+///
+/// Takes the same `rename` and `flatten` attributes as `FromScope`, so what it writes reads
+/// back as the same value.
+#[proc_macro_derive(IntoScope, attributes(ipml))]
+pub fn derive_into_scope(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    into::derive(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
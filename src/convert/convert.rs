@@ -0,0 +1,33 @@
+use crate::prelude::*;
+
+/// A type that can be read from a value, as the field of a struct read from a scope
+pub trait FromValue: Sized {
+    fn from_value(value: &Value) -> Result<Self, RuntimeError>;
+
+    // What a field of this type is when the scope has no entry for it
+    fn missing() -> Result<Self, RuntimeError> {
+        Err(RuntimeError::new("[Convert] missing field".to_string()))
+    }
+}
+
+/// A type that can be written as a value
+pub trait IntoValue {
+    fn to_value(&self) -> Result<Value, RuntimeError>;
+}
+
+/// A type that can be read from the entries of a scope, usually through `#[derive(FromScope)]`
+pub trait FromScope: Sized {
+    fn from_scope(scope: &mut Scope) -> Result<Self, RuntimeError>;
+}
+
+/// A type that can be written as the entries of a scope, usually through `#[derive(IntoScope)]`
+pub trait IntoScope {
+    fn set_into(&self, scope: &mut Scope) -> Result<(), RuntimeError>;
+
+    /// Writes it into a new scope
+    fn to_scope(&self) -> Result<ScopeRc, RuntimeError> {
+        let scope = rc_cell(Scope::new());
+        self.set_into(&mut scope.borrow_mut())?;
+        Ok(scope)
+    }
+}
@@ -0,0 +1,175 @@
+// Helpers called by the code of #[derive(FromScope, IntoScope)]
+use crate::prelude::*;
+
+pub fn expected(expected: &str, value: &Value) -> RuntimeError {
+    RuntimeError::new(format!(
+        "[Convert] expected {}, got {}",
+        expected,
+        value.kind()
+    ))
+}
+
+// A sub-scope of the name, or else a value of it, looked up in the scope itself only
+fn entry(scope: &mut Scope, name: &str) -> Result<Option<Value>, RuntimeError> {
+    let name = [name.to_string()];
+    if scope.get_scopes().contains_key(&name[0]) {
+        Ok(Some(Value::Scope(scope.query_scope(&name)?)))
+    } else if scope.get_values().contains_key(&name[0]) {
+        Ok(Some(scope.query_value(&name)?.borrow().clone()))
+    } else {
+        Ok(None)
+    }
+}
+
+pub fn read<T: FromValue>(scope: &mut Scope, name: &str) -> Result<T, RuntimeError> {
+    match entry(scope, name)? {
+        Some(value) => T::from_value(&value),
+        None => T::missing(),
+    }
+    .map_err(|e| e.within(name))
+}
+
+// Like read, but a missing field takes the default
+pub fn read_or<T: FromValue>(
+    scope: &mut Scope,
+    name: &str,
+    default: impl FnOnce() -> T,
+) -> Result<T, RuntimeError> {
+    match entry(scope, name)? {
+        Some(value) => T::from_value(&value).map_err(|e| e.within(name)),
+        None => Ok(default()),
+    }
+}
+
+// Sub-scopes are linked as children, so they are written as [name] blocks
+pub fn write<T: IntoValue + ?Sized>(
+    scope: &mut Scope,
+    name: &str,
+    value: &T,
+) -> Result<(), RuntimeError> {
+    let name = [name.to_string()];
+    match value.to_value()? {
+        Value::Scope(child) => scope.set_scope(&name, child),
+        value => scope.set_value(&name, rc_cell(value)),
+    }
+}
+
+// Runs a validation hook, placing its error at the field if there is one
+pub fn validate<T, E: Display>(
+    name: Option<&str>,
+    value: &T,
+    hook: impl FnOnce(&T) -> Result<(), E>,
+) -> Result<(), RuntimeError> {
+    hook(value).map_err(|e| {
+        let e = RuntimeError::new(format!("[Convert] {}", e));
+        match name {
+            Some(name) => e.within(name),
+            None => e,
+        }
+    })
+}
+
+// Reads a scope or map value as a scope
+pub fn with_scope<T>(
+    value: &Value,
+    f: impl FnOnce(&mut Scope) -> Result<T, RuntimeError>,
+) -> Result<T, RuntimeError> {
+    match value {
+        Value::Scope(scope) => f(&mut scope.borrow_mut()),
+        Value::Map(map) => {
+            let mut scope = Scope::new();
+            for (key, value) in map.iter() {
+                scope.set_value(std::slice::from_ref(key), value.clone())?;
+            }
+            f(&mut scope)
+        }
+        value => Err(expected("Scope", value)),
+    }
+}
+
+pub fn to_scope_value<T: IntoScope + ?Sized>(value: &T) -> Result<Value, RuntimeError> {
+    Ok(Value::Scope(value.to_scope()?))
+}
+
+fn single(mut entries: Vec<(String, Value)>) -> Result<(String, Option<Value>), RuntimeError> {
+    match entries.len() {
+        1 => {
+            let (name, value) = entries.pop().unwrap();
+            Ok((name, Some(value)))
+        }
+        len => Err(RuntimeError::new(format!(
+            "[Convert] expected a single variant, got {} entries",
+            len
+        ))),
+    }
+}
+
+// The variant named by a value, a name alone for a unit variant or a single entry of a map or
+// scope, which holds the fields of the variant
+pub fn variant(value: &Value) -> Result<(String, Option<Value>), RuntimeError> {
+    let entries = match value {
+        Value::Str(name) => return Ok((name.clone(), None)),
+        Value::Map(map) => map
+            .iter()
+            .map(|(key, value)| (key.clone(), value.borrow().clone()))
+            .collect(),
+        Value::Scope(scope) => scope.borrow().entries()?,
+        value => return Err(expected("a variant", value)),
+    };
+    single(entries)
+}
+
+pub fn scope_variant(scope: &mut Scope) -> Result<(String, Option<Value>), RuntimeError> {
+    single(scope.entries()?)
+}
+
+// The fields of a variant, which a unit variant does not have
+pub fn fields(name: &str, fields: Option<Value>) -> Result<Value, RuntimeError> {
+    fields.ok_or_else(|| {
+        RuntimeError::new("[Convert] expected fields, got none".to_string()).within(name)
+    })
+}
+
+// Reads the items of a list value for a tuple variant
+pub fn tuple(value: &Value, len: usize) -> Result<Vec<Value>, RuntimeError> {
+    match value {
+        Value::List(list) if list.len() == len => {
+            Ok(list.iter().map(|item| item.borrow().clone()).collect())
+        }
+        Value::List(list) => Err(RuntimeError::new(format!(
+            "[Convert] expected {} items, got {}",
+            len,
+            list.len()
+        ))),
+        value => Err(expected("List", value)),
+    }
+}
+
+pub fn unknown_variant(name: &str, variants: &[&str]) -> RuntimeError {
+    RuntimeError::new(format!(
+        "[Convert] unknown variant {}, expected one of {}",
+        name,
+        variants.join(", ")
+    ))
+}
+
+// A variant with fields, written as a map of its name to them
+pub fn variant_value(name: &str, value: Value) -> Value {
+    let mut map = Map::new();
+    map.insert(name.to_string(), rc_cell(value));
+    Value::Map(map)
+}
+
+// Writes a variant into a scope as its only entry, a unit variant holding null
+pub fn set_variant(scope: &mut Scope, value: Value) -> Result<(), RuntimeError> {
+    match value {
+        Value::Str(name) => write(scope, &name, &Value::Null),
+        Value::Map(map) => {
+            for (name, value) in map.iter() {
+                write(scope, name, &*value.borrow())?;
+            }
+            Ok(())
+        }
+        value => Err(expected("a variant", &value)),
+    }
+}
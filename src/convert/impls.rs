@@ -0,0 +1,224 @@
+use super::field::expected;
+use crate::prelude::*;
+
+// Integers are read from ints and sizes, checking that they fit
+macro_rules! impl_int {
+    ($($t:ty),*) => {$(
+        impl FromValue for $t {
+            fn from_value(value: &Value) -> Result<Self, RuntimeError> {
+                let i = match value {
+                    Value::Int(i) => *i as i128,
+                    Value::Size(s) => *s as i128,
+                    value => return Err(expected("Int", value)),
+                };
+                <$t>::try_from(i).map_err(|_| {
+                    RuntimeError::new(format!(
+                        "[Convert] {} is out of range for {}",
+                        i,
+                        stringify!($t)
+                    ))
+                })
+            }
+        }
+
+        impl IntoValue for $t {
+            fn to_value(&self) -> Result<Value, RuntimeError> {
+                i64::try_from(*self).map(Value::Int).map_err(|_| {
+                    RuntimeError::new(format!("[Convert] {} is out of range for Int", self))
+                })
+            }
+        }
+    )*};
+}
+
+impl_int!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+impl FromValue for f64 {
+    fn from_value(value: &Value) -> Result<Self, RuntimeError> {
+        match value {
+            Value::Float(f) => Ok(*f),
+            Value::Int(i) => Ok(*i as f64),
+            value => Err(expected("Float", value)),
+        }
+    }
+}
+
+impl IntoValue for f64 {
+    fn to_value(&self) -> Result<Value, RuntimeError> {
+        Ok(Value::Float(*self))
+    }
+}
+
+impl FromValue for f32 {
+    fn from_value(value: &Value) -> Result<Self, RuntimeError> {
+        f64::from_value(value).map(|f| f as f32)
+    }
+}
+
+impl IntoValue for f32 {
+    fn to_value(&self) -> Result<Value, RuntimeError> {
+        Ok(Value::Float(*self as f64))
+    }
+}
+
+impl FromValue for bool {
+    fn from_value(value: &Value) -> Result<Self, RuntimeError> {
+        match value {
+            Value::Bool(b) => Ok(*b),
+            value => Err(expected("Bool", value)),
+        }
+    }
+}
+
+impl IntoValue for bool {
+    fn to_value(&self) -> Result<Value, RuntimeError> {
+        Ok(Value::Bool(*self))
+    }
+}
+
+impl FromValue for String {
+    fn from_value(value: &Value) -> Result<Self, RuntimeError> {
+        match value {
+            Value::Str(s) => Ok(s.clone()),
+            value => Err(expected("Str", value)),
+        }
+    }
+}
+
+impl IntoValue for String {
+    fn to_value(&self) -> Result<Value, RuntimeError> {
+        Ok(Value::Str(self.clone()))
+    }
+}
+
+impl IntoValue for str {
+    fn to_value(&self) -> Result<Value, RuntimeError> {
+        Ok(Value::Str(self.to_string()))
+    }
+}
+
+impl FromValue for std::time::Duration {
+    fn from_value(value: &Value) -> Result<Self, RuntimeError> {
+        match value {
+            Value::Duration(d) => Ok(*d),
+            value => Err(expected("Duration", value)),
+        }
+    }
+}
+
+impl IntoValue for std::time::Duration {
+    fn to_value(&self) -> Result<Value, RuntimeError> {
+        Ok(Value::Duration(*self))
+    }
+}
+
+// Null and a missing field are both none
+impl<T: FromValue> FromValue for Option<T> {
+    fn from_value(value: &Value) -> Result<Self, RuntimeError> {
+        match value {
+            Value::Null => Ok(None),
+            value => T::from_value(value).map(Some),
+        }
+    }
+
+    fn missing() -> Result<Self, RuntimeError> {
+        Ok(None)
+    }
+}
+
+impl<T: IntoValue> IntoValue for Option<T> {
+    fn to_value(&self) -> Result<Value, RuntimeError> {
+        match self {
+            Some(value) => value.to_value(),
+            None => Ok(Value::Null),
+        }
+    }
+}
+
+// Bytes are read as a list of their ints, so Vec<u8> takes either
+impl<T: FromValue> FromValue for Vec<T> {
+    fn from_value(value: &Value) -> Result<Self, RuntimeError> {
+        let items: Vec<Value> = match value {
+            Value::List(list) => list.iter().map(|item| item.borrow().clone()).collect(),
+            Value::Bytes(bytes) => bytes.iter().map(|b| Value::Int(*b as i64)).collect(),
+            value => return Err(expected("List", value)),
+        };
+        items
+            .iter()
+            .enumerate()
+            .map(|(i, item)| T::from_value(item).map_err(|e| e.within(&i.to_string())))
+            .collect()
+    }
+}
+
+impl<T: IntoValue> IntoValue for Vec<T> {
+    fn to_value(&self) -> Result<Value, RuntimeError> {
+        let list = self
+            .iter()
+            .map(|item| Ok(rc_cell(item.to_value()?)))
+            .collect::<Result<VecDeque<ValueRc>, RuntimeError>>()?;
+        Ok(Value::List(list))
+    }
+}
+
+// Read from a map or the entries of a scope
+impl<T: FromValue> FromValue for HashMap<String, T> {
+    fn from_value(value: &Value) -> Result<Self, RuntimeError> {
+        let entries: Vec<(String, Value)> = match value {
+            Value::Map(map) => map
+                .iter()
+                .map(|(key, value)| (key.clone(), value.borrow().clone()))
+                .collect(),
+            Value::Scope(scope) => scope.borrow().entries()?,
+            value => return Err(expected("Map", value)),
+        };
+        entries
+            .into_iter()
+            .map(|(key, value)| {
+                let value = T::from_value(&value).map_err(|e| e.within(&key))?;
+                Ok((key, value))
+            })
+            .collect()
+    }
+}
+
+// Keys are sorted, so the map is written the same way each time
+impl<T: IntoValue> IntoValue for HashMap<String, T> {
+    fn to_value(&self) -> Result<Value, RuntimeError> {
+        let mut keys: Vec<&String> = self.keys().collect();
+        keys.sort();
+        let mut map = Map::new();
+        for key in keys {
+            map.insert(key.clone(), rc_cell(self[key].to_value()?));
+        }
+        Ok(Value::Map(map))
+    }
+}
+
+impl FromValue for Value {
+    fn from_value(value: &Value) -> Result<Self, RuntimeError> {
+        Ok(value.clone())
+    }
+
+    fn missing() -> Result<Self, RuntimeError> {
+        Ok(Value::Null)
+    }
+}
+
+impl IntoValue for Value {
+    fn to_value(&self) -> Result<Value, RuntimeError> {
+        Ok(self.clone())
+    }
+}
+
+impl<T: FromValue> FromValue for Box<T> {
+    fn from_value(value: &Value) -> Result<Self, RuntimeError> {
+        T::from_value(value).map(Box::new)
+    }
+}
+
+impl<T: IntoValue + ?Sized> IntoValue for Box<T> {
+    fn to_value(&self) -> Result<Value, RuntimeError> {
+        (**self).to_value()
+    }
+}
@@ -0,0 +1,7 @@
+mod convert;
+pub(crate) mod field;
+mod impls;
+
+pub use convert::{FromScope, FromValue, IntoScope, IntoValue};
+#[cfg(feature = "derive")]
+pub use ipml_derive::{FromScope, IntoScope};
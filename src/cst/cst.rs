@@ -0,0 +1,224 @@
+use crate::prelude::*;
+
+/// A lossless syntax tree, keeping whitespace and comments so that source can be edited
+/// and written back without changing anything else
+#[derive(Debug, Clone)]
+pub struct Cst {
+    pub root: CstBlock,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CstBlock {
+    pub nodes: Vec<CstNode>,
+    // Whitespace and comments after the last node, before the closing bracket or the end
+    pub trailing: String,
+    // Whether the block is a map literal, written in braces
+    pub map: bool,
+}
+
+impl CstBlock {
+    // The brackets the block is written in
+    pub fn brackets(&self) -> (char, char) {
+        if self.map {
+            ('{', '}')
+        } else {
+            ('(', ')')
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CstNode {
+    // Whitespace and comments before this node
+    pub trivia: String,
+    pub kind: CstKind,
+}
+
+#[derive(Debug, Clone)]
+pub enum CstKind {
+    // A single token and the exact text it was written as
+    Token { text: String, token: Token },
+    Block(CstBlock),
+}
+
+impl Cst {
+    pub fn parse(source: &str) -> Result<Self, SyntaxError> {
+        let mut parser = Parser::new(source);
+        let mut tokens = Vec::new();
+        let mut spans = Vec::new();
+        while let Some(token) = parser.tokenize() {
+            let (token, span) = token?;
+            tokens.push(token);
+            spans.push(Some(span));
+        }
+        // Reject anything the normal parser would, so the tree below is always balanced
+        let mut errors = Vec::new();
+        parse_slice(&mut BlockVec::new(), &tokens, &spans, None, &mut errors);
+        if let Some(e) = errors.into_iter().next() {
+            return Err(e);
+        }
+
+        let mut stack = vec![(String::new(), CstBlock::default())];
+        let mut prev = 0;
+        for (token, span) in tokens.into_iter().zip(spans.into_iter().flatten()) {
+            let trivia = source[prev..span.start].to_string();
+            let text = source[span.start..span.end].to_string();
+            prev = span.end;
+            match token {
+                Token::Operator(ref op) if op == "(" || op == "{" => stack.push((
+                    trivia,
+                    CstBlock {
+                        map: op == "{",
+                        ..Default::default()
+                    },
+                )),
+                Token::Operator(ref op) if op == ")" || op == "}" => {
+                    let (open_trivia, mut block) = stack.pop().unwrap();
+                    block.trailing = trivia;
+                    stack.last_mut().unwrap().1.nodes.push(CstNode {
+                        trivia: open_trivia,
+                        kind: CstKind::Block(block),
+                    });
+                }
+                _ => stack.last_mut().unwrap().1.nodes.push(CstNode {
+                    trivia,
+                    kind: CstKind::Token { text, token },
+                }),
+            }
+        }
+        let (_, mut root) = stack.pop().unwrap();
+        root.trailing = source[prev..].to_string();
+        Ok(Self { root })
+    }
+
+    // Finds the nodes of the value last assigned to path, following scopes fed with blocks
+    pub fn find(&self, path: &[String]) -> Option<&[CstNode]> {
+        let (location, range) = self.root.locate(path)?;
+        let mut block = &self.root;
+        for i in location {
+            match &block.nodes[i].kind {
+                CstKind::Block(inner) => block = inner,
+                CstKind::Token { .. } => unreachable!(),
+            }
+        }
+        Some(&block.nodes[range.0..range.1])
+    }
+
+    // Replaces the value last assigned to path with the given source text, keeping the
+    // surrounding layout, or appends a new assignment if the path is never assigned
+    pub fn set_value(&mut self, path: &[String], text: &str) -> Result<(), RuntimeError> {
+        let mut nodes = Cst::parse(text)?.root.nodes;
+        if CstBlock::items(&nodes).len() != 1 {
+            return Err(RuntimeError::new(format!(
+                "[Cst] Expected a single value, but got {}",
+                text
+            )));
+        }
+        match self.root.locate(path) {
+            Some((location, range)) => {
+                let mut block = &mut self.root;
+                for i in location {
+                    match &mut block.nodes[i].kind {
+                        CstKind::Block(inner) => block = inner,
+                        CstKind::Token { .. } => unreachable!(),
+                    }
+                }
+                nodes[0].trivia = block.nodes[range.0].trivia.clone();
+                block.nodes.splice(range.0..range.1, nodes);
+            }
+            None => {
+                let symbol = Syntax::default().quote_path(path);
+                let first = if self.root.nodes.is_empty() { "" } else { "\n" };
+                self.root.nodes.push(CstNode {
+                    trivia: first.to_string(),
+                    kind: CstKind::Token {
+                        text: symbol,
+                        token: Token::Symbol(path.to_vec()),
+                    },
+                });
+                self.root.nodes.push(CstNode {
+                    trivia: " ".to_string(),
+                    kind: CstKind::Token {
+                        text: "=".to_string(),
+                        token: Token::Operator("=".to_string()),
+                    },
+                });
+                nodes[0].trivia = " ".to_string();
+                self.root.nodes.extend(nodes);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl CstBlock {
+    // Groups nodes into items, so that a decorator and its block count as one
+    pub fn items(nodes: &[CstNode]) -> Vec<(usize, usize)> {
+        let mut items = Vec::new();
+        let mut i = 0;
+        while i < nodes.len() {
+            let begin = i;
+            if let CstKind::Token {
+                token: Token::Decorator(_),
+                ..
+            } = nodes[i].kind
+            {
+                i += 1;
+            }
+            i += 1;
+            items.push((begin, i.min(nodes.len())));
+        }
+        items
+    }
+
+    // The block of an item, skipping its decorator if any
+    fn item_block(&self, item: (usize, usize)) -> Option<usize> {
+        match self.nodes[item.1 - 1].kind {
+            CstKind::Block(_) => Some(item.1 - 1),
+            CstKind::Token { .. } => None,
+        }
+    }
+
+    // Returns the indices of the blocks leading to the value last assigned to path, and the
+    // node range of that value
+    fn locate(&self, path: &[String]) -> Option<(Vec<usize>, (usize, usize))> {
+        let items = Self::items(&self.nodes);
+        let mut found = None;
+        for triple in items.chunks_exact(3) {
+            let (lhs, op, rhs) = (
+                &self.nodes[triple[0].0],
+                &self.nodes[triple[1].0],
+                triple[2],
+            );
+            let (lhs, op) = match (&lhs.kind, &op.kind) {
+                (
+                    CstKind::Token { token: lhs, .. },
+                    CstKind::Token {
+                        token: Token::Operator(op),
+                        ..
+                    },
+                ) => (lhs, op.as_str()),
+                _ => continue,
+            };
+            match (lhs, op) {
+                (Token::Symbol(name), "=" | "~" | "?=") if name.as_slice() == path => {
+                    found = Some((Vec::new(), rhs));
+                }
+                (Token::Tag(name), ":" | "=")
+                    if path.len() > name.len() && path.starts_with(name) =>
+                {
+                    if let Some(i) = self.item_block(rhs) {
+                        if let CstKind::Block(block) = &self.nodes[i].kind {
+                            if let Some((mut location, range)) = block.locate(&path[name.len()..]) {
+                                location.insert(0, i);
+                                found = Some((location, range));
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        found
+    }
+}
@@ -0,0 +1,275 @@
+use super::*;
+use crate::prelude::*;
+
+const INDENT: &str = "    ";
+// Blocks that may be written on one line are kept so only up to this width
+const INLINE_WIDTH: usize = 80;
+
+// What trivia holds, apart from plain whitespace
+#[derive(Debug, Default)]
+struct Trivia {
+    // A comment on the same line as the previous node
+    trailing: Option<String>,
+    // Comments on their own lines, None standing for a blank line
+    lines: Vec<Option<String>>,
+}
+
+impl Trivia {
+    fn new(trivia: &str) -> Self {
+        let mut result = Trivia::default();
+        let mut lines = trivia.split('\n');
+        let first = lines.next().unwrap_or("").trim();
+        if !first.is_empty() {
+            result.trailing = Some(first.to_string());
+        }
+        let lines: Vec<&str> = lines.collect();
+        // The last line is only the indentation of the node itself
+        for (i, line) in lines.iter().enumerate() {
+            let line = line.trim();
+            if !line.is_empty() {
+                result.lines.push(Some(line.to_string()));
+            } else if i + 1 < lines.len() {
+                result.lines.push(None);
+            }
+        }
+        result
+    }
+
+    fn has_comments(&self) -> bool {
+        self.trailing.is_some() || self.lines.iter().any(|line| line.is_some())
+    }
+
+    fn has_blank(&self) -> bool {
+        self.lines.iter().any(|line| line.is_none())
+    }
+}
+
+// Whether the block is a sequence of triples, the middle one of each being an operator
+fn is_triples(block: &CstBlock) -> bool {
+    let items = CstBlock::items(&block.nodes);
+    !items.is_empty()
+        && items.len().is_multiple_of(3)
+        && items.chunks_exact(3).all(|triple| {
+            matches!(
+                block.nodes[triple[1].0].kind,
+                CstKind::Token {
+                    token: Token::Operator(_),
+                    ..
+                }
+            )
+        })
+}
+
+fn has_comments(block: &CstBlock) -> bool {
+    Trivia::new(&block.trailing).has_comments()
+        || block.nodes.iter().any(|node| {
+            Trivia::new(&node.trivia).has_comments()
+                || matches!(&node.kind, CstKind::Block(block) if has_comments(block))
+        })
+}
+
+fn operator(node: &CstNode) -> Option<&str> {
+    match &node.kind {
+        CstKind::Token {
+            token: Token::Operator(op),
+            ..
+        } => Some(op),
+        _ => None,
+    }
+}
+
+struct Printer {
+    out: String,
+}
+
+impl Printer {
+    // Appends a comment to the end of the last line, or on its own line if there is none
+    fn trailing(&mut self, comment: &Option<String>, depth: usize) {
+        if let Some(comment) = comment {
+            if self.out.is_empty() {
+                self.comment(comment, depth);
+            } else {
+                self.out.pop();
+                self.out.push_str(&format!("  {}\n", comment));
+            }
+        }
+    }
+
+    fn comment(&mut self, comment: &str, depth: usize) {
+        self.out
+            .push_str(&format!("{}{}\n", INDENT.repeat(depth), comment));
+    }
+
+    // Writes comments on their own lines, keeping at most one blank line in a row
+    fn lines(&mut self, trivia: &Trivia, depth: usize) {
+        for line in trivia.lines.iter() {
+            match line {
+                Some(comment) => self.comment(comment, depth),
+                None => {
+                    if !self.out.is_empty() && !self.out.ends_with("\n\n") {
+                        self.out.push('\n');
+                    }
+                }
+            }
+        }
+    }
+
+    // Renders an item, which is a token, a block, or a decorator followed by a block
+    fn item(&self, block: &CstBlock, item: (usize, usize), depth: usize, call: bool) -> String {
+        let mut result = String::new();
+        for node in &block.nodes[item.0..item.1] {
+            if !result.is_empty() {
+                result.push(' ');
+            }
+            match &node.kind {
+                CstKind::Token { text, .. } => result.push_str(text),
+                CstKind::Block(inner) => result.push_str(&self.block(inner, depth, call)),
+            }
+        }
+        result
+    }
+
+    // Renders a block from its opening to its closing bracket, the closing one at depth
+    // Map literals and call arguments holding a single triple are kept on one line
+    fn block(&self, block: &CstBlock, depth: usize, call: bool) -> String {
+        let (open, close) = block.brackets();
+        if block.nodes.is_empty() && !has_comments(block) {
+            return format!("{}{}", open, close);
+        }
+        let triples = is_triples(block);
+        let single = CstBlock::items(&block.nodes).len() == 3;
+        if !has_comments(block) && (!triples || ((call || block.map) && single)) {
+            let inline = if triples {
+                let mut printer = Printer { out: String::new() };
+                printer.triples(block, 0);
+                printer.out.trim_end().to_string()
+            } else {
+                CstBlock::items(&block.nodes)
+                    .into_iter()
+                    .map(|item| self.item(block, item, depth, false))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            };
+            if !inline.contains('\n') && inline.len() + 2 <= INLINE_WIDTH {
+                return format!("{}{}{}", open, inline, close);
+            }
+        }
+        let mut printer = Printer { out: String::new() };
+        if triples {
+            printer.triples(block, depth + 1);
+        } else {
+            printer.list(block, depth + 1);
+        }
+        let trailing = Trivia::new(&block.trailing);
+        printer.trailing(&trailing.trailing, depth + 1);
+        printer.lines(&trailing, depth + 1);
+        while printer.out.ends_with("\n\n") {
+            printer.out.pop();
+        }
+        format!("{}\n{}{}{}", open, printer.out, INDENT.repeat(depth), close)
+    }
+
+    // Writes every item of a block on its own line
+    fn list(&mut self, block: &CstBlock, depth: usize) {
+        for item in CstBlock::items(&block.nodes) {
+            let trivia = Trivia::new(&block.nodes[item.0].trivia);
+            self.trailing(&trivia.trailing, depth);
+            self.lines(&trivia, depth);
+            let text = self.item(block, item, depth, false);
+            self.out
+                .push_str(&format!("{}{}\n", INDENT.repeat(depth), text));
+        }
+    }
+
+    // Writes one triple per line, aligning the operators of consecutive triples
+    fn triples(&mut self, block: &CstBlock, depth: usize) {
+        let items = CstBlock::items(&block.nodes);
+        let triples: Vec<&[(usize, usize)]> = items.chunks_exact(3).collect();
+
+        // Only single-line triples are aligned, in runs broken by blank lines and comments
+        let mut widths = vec![0; triples.len()];
+        let mut begin = 0;
+        for i in 0..=triples.len() {
+            let aligned = |j: usize| {
+                let triple = triples[j];
+                operator(&block.nodes[triple[1].0]) != Some("!")
+                    && !self.item(block, triple[0], depth, false).contains('\n')
+                    && !self.item(block, triple[2], depth, false).contains('\n')
+            };
+            let breaks = i == triples.len() || !aligned(i) || {
+                let trivia = Trivia::new(&block.nodes[triples[i][0].0].trivia);
+                trivia.has_blank() || trivia.lines.iter().any(|line| line.is_some())
+            };
+            if breaks {
+                let width = (begin..i)
+                    .map(|j| {
+                        self.item(block, triples[j][0], depth, false)
+                            .chars()
+                            .count()
+                    })
+                    .max()
+                    .unwrap_or(0);
+                for w in widths.iter_mut().take(i).skip(begin) {
+                    *w = width;
+                }
+                begin = if i < triples.len() && aligned(i) {
+                    i
+                } else {
+                    i + 1
+                };
+            }
+        }
+
+        for (triple, width) in triples.iter().zip(widths) {
+            let trivia = Trivia::new(&block.nodes[triple[0].0].trivia);
+            self.trailing(&trivia.trailing, depth);
+            self.lines(&trivia, depth);
+            // Comments inside a triple are moved before it
+            for node in &block.nodes[triple[0].0 + 1..triple[2].1] {
+                let trivia = Trivia::new(&node.trivia);
+                for comment in trivia.trailing.iter().chain(trivia.lines.iter().flatten()) {
+                    self.comment(comment, depth);
+                }
+            }
+
+            let op = operator(&block.nodes[triple[1].0]).unwrap();
+            let lhs = self.item(block, triple[0], depth, false);
+            let rhs = self.item(block, triple[2], depth, op == "!");
+            let line = if op == "!" {
+                let rhs_is_block = matches!(block.nodes[triple[2].1 - 1].kind, CstKind::Block(_))
+                    && triple[2].1 - triple[2].0 == 1;
+                format!("{}!{}{}", lhs, if rhs_is_block { "" } else { " " }, rhs)
+            } else {
+                let pad = width.saturating_sub(lhs.chars().count());
+                format!("{}{} {} {}", lhs, " ".repeat(pad), op, rhs)
+            };
+            self.out
+                .push_str(&format!("{}{}\n", INDENT.repeat(depth), line));
+        }
+    }
+}
+
+impl Cst {
+    /// Prints the tree as canonical source, one triple per line with aligned operators,
+    /// blocks indented by depth, and comments kept
+    pub fn format(&self) -> String {
+        let mut printer = Printer { out: String::new() };
+        if is_triples(&self.root) {
+            printer.triples(&self.root, 0);
+        } else {
+            printer.list(&self.root, 0);
+        }
+        let trailing = Trivia::new(&self.root.trailing);
+        printer.trailing(&trailing.trailing, 0);
+        printer.lines(&trailing, 0);
+        while printer.out.ends_with("\n\n") {
+            printer.out.pop();
+        }
+        printer.out
+    }
+}
+
+// Formats source text, failing only if it cannot be parsed
+pub fn format_source(source: &str) -> Result<String, SyntaxError> {
+    Ok(Cst::parse(source)?.format())
+}
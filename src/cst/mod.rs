@@ -0,0 +1,5 @@
+mod cst;
+mod format;
+
+pub use cst::{Cst, CstBlock, CstKind, CstNode};
+pub use format::format_source;
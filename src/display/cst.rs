@@ -0,0 +1,29 @@
+use crate::prelude::*;
+
+impl Display for Cst {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{}", self.root)
+    }
+}
+
+impl Display for CstBlock {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        for node in self.nodes.iter() {
+            write!(f, "{}", node)?;
+        }
+        write!(f, "{}", self.trailing)
+    }
+}
+
+impl Display for CstNode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{}", self.trivia)?;
+        match &self.kind {
+            CstKind::Token { text, .. } => write!(f, "{}", text),
+            CstKind::Block(block) => {
+                let (open, close) = block.brackets();
+                write!(f, "{}{}{}", open, block, close)
+            }
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use crate::prelude::*;
+
+fn write_json_str(s: &str, out: &mut String) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\u{8}' => out.push_str("\\b"),
+            '\u{c}' => out.push_str("\\f"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+fn newline(depth: usize, out: &mut String) {
+    out.push('\n');
+    out.push_str(&"  ".repeat(depth));
+}
+
+// Writes the entries as a JSON object, each on its own line
+fn write_object<'a>(
+    entries: impl Iterator<Item = (&'a String, Value)>,
+    depth: usize,
+    out: &mut String,
+) -> Result<(), RuntimeError> {
+    out.push('{');
+    let mut empty = true;
+    for (key, value) in entries {
+        if !std::mem::take(&mut empty) {
+            out.push(',');
+        }
+        newline(depth + 1, out);
+        write_json_str(key, out);
+        out.push_str(": ");
+        write_json_value(&value, depth + 1, out)?;
+    }
+    if !empty {
+        newline(depth, out);
+    }
+    out.push('}');
+    Ok(())
+}
+
+fn write_json_value(value: &Value, depth: usize, out: &mut String) -> Result<(), RuntimeError> {
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(b) => out.push_str(&b.to_string()),
+        Value::Int(i) => out.push_str(&i.to_string()),
+        Value::Float(f) if !f.is_finite() => {
+            return Err(RuntimeError::new(format!(
+                "[Json] {} has no JSON form",
+                value
+            )))
+        }
+        // Debug keeps the fraction, so a whole float reads back as a float
+        Value::Float(f) => out.push_str(&format!("{:?}", f)),
+        Value::Str(s) => write_json_str(s, out),
+        Value::Bytes(b) => write_json_str(&encode_base64(b), out),
+        Value::Duration(_) | Value::Size(_) => write_json_str(&value.to_string(), out),
+        Value::List(list) => {
+            out.push('[');
+            for (i, item) in list.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                newline(depth + 1, out);
+                write_json_value(&item.borrow(), depth + 1, out)?;
+            }
+            if !list.is_empty() {
+                newline(depth, out);
+            }
+            out.push(']');
+        }
+        Value::Map(map) => write_object(
+            map.iter().map(|(key, value)| (key, value.borrow().clone())),
+            depth,
+            out,
+        )?,
+        Value::Scope(scope) => write_json_scope(&scope.borrow(), depth, out)?,
+        Value::Functor(_) => {
+            return Err(RuntimeError::new(format!(
+                "[Json] {} has no JSON form",
+                value
+            )))
+        }
+    }
+    Ok(())
+}
+
+// Like write_scope, leaves out functors and anonymous scopes and sorts the rest by name
+fn write_json_scope(scope: &Scope, depth: usize, out: &mut String) -> Result<(), RuntimeError> {
+    let entries = scope.entries()?;
+    write_object(
+        entries.iter().map(|(key, value)| (key, value.clone())),
+        depth,
+        out,
+    )
+}
+
+impl Scope {
+    /// Writes the scope as a JSON object, with sub-scopes as nested objects
+    ///
+    /// Lists become arrays and maps objects, keeping their order, while the entries of a
+    /// scope are sorted by name. Functors of a scope and anonymous scopes are left out, but a
+    /// functor anywhere else, as well as NaN and infinite floats, has no JSON form and fails.
+    /// Bytes are written as base64 strings and durations and sizes as strings of their
+    /// literals like "1500ms", so they read back as strings. A value and a sub-scope of the
+    /// same name would be the same key, and fail.
+    pub fn to_json(&self) -> Result<String, RuntimeError> {
+        let mut out = String::new();
+        write_json_scope(self, 0, &mut out)?;
+        out.push('\n');
+        Ok(out)
+    }
+}
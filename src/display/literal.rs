@@ -0,0 +1,142 @@
+use crate::prelude::*;
+
+// Writes a string in quotes, escaping anything the parser would not read back as is
+fn write_str(s: &str, out: &mut String) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\0' => out.push_str("\\0"),
+            c if c.is_control() => out.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+// A name of a single section, which may only be left unquoted if it is not empty
+fn write_name(name: &str, syntax: &Syntax, out: &mut String) {
+    if name.is_empty() {
+        write_str(name, out);
+    } else {
+        out.push_str(&syntax.quote_path(&[name.to_string()]));
+    }
+}
+
+fn write_value(value: &Value, syntax: &Syntax, out: &mut String) -> Result<(), RuntimeError> {
+    match value {
+        Value::Float(f) if f.is_nan() => out.push_str("nan"),
+        Value::Float(f) if f.is_infinite() => out.push_str(if *f > 0.0 { "inf" } else { "-inf" }),
+        // Debug keeps the fraction of whole floats and writes the shortest exact digits
+        Value::Float(f) => out.push_str(&format!("{:?}", f)),
+        Value::Str(s) => write_str(s, out),
+        Value::List(list) => {
+            out.push('(');
+            for (i, item) in list.iter().enumerate() {
+                if i > 0 {
+                    out.push(' ');
+                }
+                write_value(&item.borrow(), syntax, out)?;
+            }
+            out.push(')');
+        }
+        Value::Map(map) => {
+            out.push('{');
+            for (i, (key, value)) in map.iter().enumerate() {
+                if i > 0 {
+                    out.push(' ');
+                }
+                write_name(key, syntax, out);
+                out.push_str(" = ");
+                write_value(&value.borrow(), syntax, out)?;
+            }
+            out.push('}');
+        }
+        Value::Scope(scope) => {
+            out.push_str("indep(");
+            write_scope(&scope.borrow(), syntax, out)?;
+            out.push(')');
+        }
+        Value::Functor(_) => {
+            return Err(RuntimeError::new(format!(
+                "[Literal] {} cannot be written as a literal",
+                value
+            )))
+        }
+        // Everything else is displayed as its literal
+        value => out.push_str(&value.to_string()),
+    }
+    Ok(())
+}
+
+// Writes the values and then the sub-scopes of a scope, each sorted by name
+// Functors are left out, as they are inserted by the host, and so are anonymous scopes
+fn write_scope(scope: &Scope, syntax: &Syntax, out: &mut String) -> Result<(), RuntimeError> {
+    let mut values: Vec<_> = scope
+        .get_values()
+        .iter()
+        .filter(|(_, value)| !matches!(*value.borrow(), Value::Functor(_)))
+        .collect();
+    values.sort_by(|a, b| a.0.cmp(b.0));
+    let mut scopes: Vec<_> = scope
+        .get_scopes()
+        .iter()
+        .filter(|(name, _)| *name != ANONYMOUS)
+        .collect();
+    scopes.sort_by(|a, b| a.0.cmp(b.0));
+    let mut first = true;
+    for (name, value) in values {
+        if !std::mem::take(&mut first) {
+            out.push(' ');
+        }
+        out.push_str(&syntax.quote_path(std::slice::from_ref(name)));
+        out.push_str(" = ");
+        write_value(&value.borrow(), syntax, out)?;
+    }
+    for (name, scope) in scopes {
+        if !std::mem::take(&mut first) {
+            out.push(' ');
+        }
+        out.push('[');
+        out.push_str(&syntax.quote_path(std::slice::from_ref(name)));
+        // Assigned rather than fed, so a name also used by an outer scope is not taken for it
+        out.push_str("] = (");
+        write_scope(&scope.borrow(), syntax, out)?;
+        out.push(')');
+    }
+    Ok(())
+}
+
+impl Value {
+    /// Writes the value as IPML source, which parses back to an equal value
+    ///
+    /// Scopes are written as `indep` blocks without their functors, and a functor itself has
+    /// no literal, so it fails
+    pub fn to_ipml_literal(&self) -> Result<String, RuntimeError> {
+        let mut out = String::new();
+        write_value(self, &Syntax::default(), &mut out)?;
+        Ok(out)
+    }
+}
+
+impl Scope {
+    /// Writes the scope as formatted IPML source, which fed to an empty scope gives an
+    /// equal one
+    ///
+    /// Values and sub-scopes are sorted by name, and functors and anonymous scopes are left out
+    pub fn to_ipml_string(&self) -> Result<String, RuntimeError> {
+        let mut out = String::new();
+        write_scope(self, &Syntax::default(), &mut out)?;
+        Ok(format_source(&out)?)
+    }
+
+    pub fn write_ipml(&self, writer: &mut impl std::io::Write) -> Result<(), RuntimeError> {
+        writer
+            .write_all(self.to_ipml_string()?.as_bytes())
+            .map_err(|e| RuntimeError::new(format!("[Write] {}", e)))
+    }
+}
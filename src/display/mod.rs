@@ -1,3 +1,7 @@
-mod token;
+mod cst;
+mod json;
+mod literal;
 mod scope;
+mod span;
+mod token;
 mod value;
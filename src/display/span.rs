@@ -0,0 +1,10 @@
+use crate::prelude::*;
+
+impl Display for Span {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        if self.file != 0 {
+            write!(f, "file #{}, ", self.file)?;
+        }
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
@@ -5,8 +5,8 @@ impl Display for Token {
         write!(f, "T(")?;
         match self {
             Token::Value(value) => write!(f, "{}", value)?,
-            Token::Symbol(name) => write!(f, "{}", name.join("."))?,
-            Token::Tag(name) => write!(f, "[{}]", name.join("."))?,
+            Token::Symbol(name) => write!(f, "{}", Syntax::default().quote_path(name))?,
+            Token::Tag(name) => write!(f, "[{}]", Syntax::default().quote_path(name))?,
             Token::Block(_block) => {
                 /*
                 write!(f, "{{")?;
@@ -8,6 +8,9 @@ impl Display for Value {
             Value::Int(i) => write!(f, "{}", i),
             Value::Float(fl) => write!(f, "{}", fl),
             Value::Str(s) => write!(f, "{}", s),
+            Value::Bytes(b) => write!(f, "hex\"{}\"", encode_hex(b)),
+            Value::Duration(d) => write!(f, "{}", format_duration(d)),
+            Value::Size(s) => write!(f, "{}", format_size(*s)),
             Value::List(list) => {
                 write!(f, "[")?;
                 let mut first = true;
@@ -21,6 +24,20 @@ impl Display for Value {
                 }
                 write!(f, "]")
             }
+            Value::Map(map) => {
+                write!(f, "{{")?;
+                let mut first = true;
+                for (key, value) in map.iter() {
+                    if first {
+                        first = false;
+                    } else {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: {}", key, value.borrow())?;
+                }
+                write!(f, "}}")
+            }
+            Value::Scope(scope) => write!(f, "{}", scope.borrow()),
             Value::Functor(functor) => write!(f, "{:?}", functor),
         }
     }
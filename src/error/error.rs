@@ -1,18 +1,49 @@
 use crate::prelude::*;
 
-pub struct SyntaxError {
-    line: usize,
-    cursor: usize,
-    msg: String,
+pub enum SyntaxError {
+    // The source itself is malformed at the line and cursor
+    Source {
+        line: usize,
+        cursor: usize,
+        msg: String,
+    },
+    // Reading the input failed, which has no position in the source
+    Io(std::io::Error),
 }
 
 impl SyntaxError {
     pub fn new(line: usize, cursor: usize, msg: String) -> Self {
-        Self { line, cursor, msg }
+        Self::Source { line, cursor, msg }
+    }
+
+    pub fn at(span: Span, msg: String) -> Self {
+        Self::new(span.line, span.column, msg)
+    }
+
+    // Both are 0 for IO errors
+    pub fn line(&self) -> usize {
+        match self {
+            Self::Source { line, .. } => *line,
+            Self::Io(_) => 0,
+        }
+    }
+
+    pub fn cursor(&self) -> usize {
+        match self {
+            Self::Source { cursor, .. } => *cursor,
+            Self::Io(_) => 0,
+        }
     }
 }
 
-impl Error for SyntaxError {}
+impl Error for SyntaxError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Source { .. } => None,
+            Self::Io(e) => Some(e),
+        }
+    }
+}
 
 impl Debug for SyntaxError {
     fn fmt(&self, f: &mut Formatter) -> FmtResult {
@@ -22,21 +53,61 @@ impl Debug for SyntaxError {
 
 impl Display for SyntaxError {
     fn fmt(&self, f: &mut Formatter) -> FmtResult {
-        write!(
-            f,
-            "IPML Syntax Error occurred at line {}, cursor {}: {}",
-            self.line, self.cursor, self.msg
-        )
+        match self {
+            Self::Source { line, cursor, msg } => write!(
+                f,
+                "IPML Syntax Error occurred at line {}, cursor {}: {}",
+                line, cursor, msg
+            ),
+            Self::Io(e) => write!(f, "IPML IO Error occurred while reading input: {}", e),
+        }
     }
 }
 
 pub struct RuntimeError {
     msg: String,
+    span: Option<Span>,
+    // Names of the fields and indices leading to the value the error occurred in, when read
+    // into a Rust type
+    path: Vec<String>,
 }
 
 impl RuntimeError {
     pub fn new(msg: String) -> Self {
-        Self { msg }
+        Self {
+            msg,
+            span: None,
+            path: Vec::new(),
+        }
+    }
+
+    // Puts the name of a field or index in front of the path, as the error leaves the value
+    // of that name
+    pub fn within(mut self, name: &str) -> Self {
+        self.path.insert(0, name.to_string());
+        self
+    }
+
+    pub fn path(&self) -> &[String] {
+        &self.path
+    }
+
+    // Attaches the span where the error occurred, keeping the innermost one if already set
+    pub fn with_span(mut self, span: Option<Span>) -> Self {
+        if self.span.is_none() {
+            self.span = span;
+        }
+        self
+    }
+
+    pub fn span(&self) -> Option<Span> {
+        self.span
+    }
+}
+
+impl From<SyntaxError> for RuntimeError {
+    fn from(e: SyntaxError) -> Self {
+        Self::new(e.to_string())
     }
 }
 
@@ -50,6 +121,12 @@ impl Debug for RuntimeError {
 
 impl Display for RuntimeError {
     fn fmt(&self, f: &mut Formatter) -> FmtResult {
-        write!(f, "{}", self.msg)
+        write!(f, "{}", self.msg)?;
+        match (self.path.is_empty(), &self.span) {
+            (true, None) => Ok(()),
+            (true, Some(span)) => write!(f, " (at {})", span),
+            (false, None) => write!(f, " (at {})", self.path.join(".")),
+            (false, Some(span)) => write!(f, " (at {}, {})", self.path.join("."), span),
+        }
     }
 }
@@ -7,6 +7,175 @@ macro_rules! string_list {
     };
 }
 
+// Turns an int into a float when the other operand is a float, leaving anything else as is
+fn promote(a: &Value, b: &Value) -> (Value, Value) {
+    match (a, b) {
+        (Value::Int(a), Value::Float(b)) => (Value::Float(*a as f64), Value::Float(*b)),
+        (Value::Float(a), Value::Int(b)) => (Value::Float(*a), Value::Float(*b as f64)),
+        (a, b) => (a.clone(), b.clone()),
+    }
+}
+
+// Only values of the same kind are ordered, after promotion
+fn compare(name: &str, a: &Value, b: &Value) -> Result<std::cmp::Ordering, RuntimeError> {
+    let (pa, pb) = promote(a, b);
+    match pa.partial_cmp(&pb) {
+        Some(ordering) if std::mem::discriminant(&pa) == std::mem::discriminant(&pb) => {
+            Ok(ordering)
+        }
+        _ => Err(RuntimeError::new(format!(
+            "[{}] Cannot compare {} and {}",
+            name, a, b
+        ))),
+    }
+}
+
+// Inserts a functor of A and B, true if the ordering of A to B passes the test
+fn comparison(
+    scope: &mut Scope,
+    name: &'static str,
+    test: fn(std::cmp::Ordering) -> bool,
+) -> Result<(), RuntimeError> {
+    scope.insert_functor(&string_list!(name), move |scope| {
+        let a = scope.query_value(&string_list!("A"))?;
+        let b = scope.query_value(&string_list!("B"))?;
+        let ordering = compare(name, &a.borrow(), &b.borrow())?;
+        Ok(rc_cell(Value::Bool(test(ordering))))
+    })
+}
+
+// Inserts a functor of A and B, failing instead of overflowing or dividing by zero
+fn binary_math(
+    scope: &mut Scope,
+    name: &'static str,
+    int: fn(i64, i64) -> Option<i64>,
+    float: fn(f64, f64) -> f64,
+) -> Result<(), RuntimeError> {
+    scope.insert_functor(&string_list!(name), move |scope| {
+        let a = scope.query_value(&string_list!("A"))?;
+        let b = scope.query_value(&string_list!("B"))?;
+        if let Some(result) = unit_math(name, &a.borrow(), &b.borrow()) {
+            return result.map(rc_cell);
+        }
+        let (a, b) = promote(&a.borrow(), &b.borrow());
+        match (a, b) {
+            (Value::Int(_), Value::Int(0)) if matches!(name, "div" | "mod") => {
+                Err(RuntimeError::new(format!("[{}] Division by zero", name)))
+            }
+            (Value::Int(a), Value::Int(b)) => match int(a, b) {
+                Some(v) => Ok(rc_cell(Value::Int(v))),
+                None => Err(RuntimeError::new(format!(
+                    "[{}] Integer overflow with {} and {}",
+                    name, a, b
+                ))),
+            },
+            (Value::Float(a), Value::Float(b)) => Ok(rc_cell(Value::Float(float(a, b)))),
+            (a, b) => Err(RuntimeError::new(format!(
+                "[{}] Expected two numbers, but got {} and {}",
+                name, a, b
+            ))),
+        }
+    })
+}
+
+// Arithmetic keeping the unit of durations and sizes, or None if neither operand has one
+// Two amounts of the same unit add, subtract and divide, and an amount scales by an int
+fn unit_math(name: &str, a: &Value, b: &Value) -> Option<Result<Value, RuntimeError>> {
+    let amount = |v: &Value| match v {
+        Value::Duration(d) => Some(d.as_nanos()),
+        Value::Size(s) => Some(*s as u128),
+        _ => None,
+    };
+    let count = |v: &Value| match v {
+        Value::Int(i) => u128::try_from(*i).ok(),
+        _ => None,
+    };
+    let unit = if amount(a).is_some() {
+        a
+    } else if amount(b).is_some() {
+        b
+    } else {
+        return None;
+    };
+    if matches!(name, "div" | "mod") && (amount(b) == Some(0) || count(b) == Some(0)) {
+        return Some(Err(RuntimeError::new(format!(
+            "[{}] Division by zero",
+            name
+        ))));
+    }
+    let same = std::mem::discriminant(a) == std::mem::discriminant(b);
+    let result = match (name, amount(a), amount(b)) {
+        ("add", Some(x), Some(y)) if same => x.checked_add(y),
+        ("sub", Some(x), Some(y)) if same => x.checked_sub(y),
+        ("mod", Some(x), Some(y)) if same => Some(x % y),
+        ("div", Some(x), Some(y)) if same => return Some(Ok(Value::Float(x as f64 / y as f64))),
+        ("mul", Some(x), None) if count(b).is_some() => x.checked_mul(count(b)?),
+        ("mul", None, Some(y)) if count(a).is_some() => y.checked_mul(count(a)?),
+        ("div", Some(x), None) if count(b).is_some() => Some(x / count(b)?),
+        _ => {
+            return Some(Err(RuntimeError::new(format!(
+                "[{}] Expected matching units or an amount and a positive int, but got {} and {}",
+                name, a, b
+            ))))
+        }
+    };
+    let value = match unit {
+        Value::Duration(_) => result.and_then(duration_from_nanos).map(Value::Duration),
+        _ => result.and_then(|n| u64::try_from(n).ok()).map(Value::Size),
+    };
+    Some(value.ok_or_else(|| {
+        RuntimeError::new(format!(
+            "[{}] Result of {} and {} is out of range",
+            name, a, b
+        ))
+    }))
+}
+
+fn unary_math(
+    scope: &mut Scope,
+    name: &'static str,
+    int: fn(i64) -> Option<i64>,
+    float: fn(f64) -> f64,
+) -> Result<(), RuntimeError> {
+    scope.insert_functor(&string_list!(name), move |scope| {
+        match &*scope.query_value(&string_list!("V"))?.borrow() {
+            Value::Int(v) => match int(*v) {
+                Some(v) => Ok(rc_cell(Value::Int(v))),
+                None => Err(RuntimeError::new(format!(
+                    "[{}] Integer overflow with {}",
+                    name, v
+                ))),
+            },
+            Value::Float(v) => Ok(rc_cell(Value::Float(float(*v)))),
+            v => Err(RuntimeError::new(format!(
+                "[{}] Expected a number, but got {}",
+                name, v
+            ))),
+        }
+    })
+}
+
+// Inserts a functor rounding a float to an int, which leaves ints as they are
+fn rounding(
+    scope: &mut Scope,
+    name: &'static str,
+    round: fn(f64) -> f64,
+) -> Result<(), RuntimeError> {
+    scope.insert_functor(&string_list!(name), move |scope| {
+        match &*scope.query_value(&string_list!("V"))?.borrow() {
+            Value::Int(v) => Ok(rc_cell(Value::Int(*v))),
+            // i64::MAX as f64 rounds up to 2^63, which is already out of range
+            Value::Float(v) if round(*v) >= i64::MIN as f64 && round(*v) < i64::MAX as f64 => {
+                Ok(rc_cell(Value::Int(round(*v) as i64)))
+            }
+            v => Err(RuntimeError::new(format!(
+                "[{}] Expected a number within the range of integers, but got {}",
+                name, v
+            ))),
+        }
+    })
+}
+
 pub fn init_functor(scope: &mut Scope) -> Result<(), RuntimeError> {
     scope.insert_functor(&string_list!("print"), |scope| {
         let v = scope.query_value(&string_list!("V"))?;
@@ -31,110 +200,98 @@ pub fn init_functor(scope: &mut Scope) -> Result<(), RuntimeError> {
         }
         Ok(rc_cell(Value::Null))
     })?;
+    scope.insert_functor(&string_list!("import"), |scope| {
+        let p = match &*scope.query_value(&string_list!("P"))?.borrow() {
+            Value::Str(p) => p.clone(),
+            p => {
+                return Err(RuntimeError::new(format!(
+                    "[import] Expected a path string, but got {}",
+                    p
+                )))
+            }
+        };
+        // The file is fed into T if given, otherwise into the calling scope
+        match scope.get_scopes().get("T").cloned() {
+            Some(target) => target.borrow_mut().import(&p)?,
+            None => match scope.get_parent() {
+                Some(parent) => parent.import(&p)?,
+                None => scope.import(&p)?,
+            },
+        }
+        Ok(rc_cell(Value::Null))
+    })?;
     scope.insert_functor(&string_list!("return"), |scope| {
-        Ok(scope.query_value(&string_list!("V"))?)
+        scope.query_value(&string_list!("V"))
     })?;
 
-    scope.insert_functor(&string_list!("add"), |scope| {
-        match (
-            &*scope.query_value(&string_list!("A"))?.borrow(),
-            &*scope.query_value(&string_list!("B"))?.borrow(),
-        ) {
-            (Value::Int(a), Value::Int(b)) => Ok(rc_cell(Value::Int(a + b))),
-            (Value::Float(a), Value::Float(b)) => Ok(rc_cell(Value::Float(a + b))),
-            (a, b) => Err(RuntimeError::new(format!(
-                "[add] Expected two numbers, but got {} and {}",
-                a, b
-            ))),
-        }
-    })?;
-    scope.insert_functor(&string_list!("sub"), |scope| {
-        match (
-            &*scope.query_value(&string_list!("A"))?.borrow(),
-            &*scope.query_value(&string_list!("B"))?.borrow(),
+    binary_math(scope, "add", i64::checked_add, |a, b| a + b)?;
+    binary_math(scope, "sub", i64::checked_sub, |a, b| a - b)?;
+    binary_math(scope, "mul", i64::checked_mul, |a, b| a * b)?;
+    binary_math(scope, "div", i64::checked_div, |a, b| a / b)?;
+    binary_math(scope, "mod", i64::checked_rem, |a, b| a % b)?;
+    scope.insert_functor(&string_list!("pow"), |scope| {
+        match promote(
+            &scope.query_value(&string_list!("A"))?.borrow(),
+            &scope.query_value(&string_list!("B"))?.borrow(),
         ) {
-            (Value::Int(a), Value::Int(b)) => Ok(rc_cell(Value::Int(a - b))),
-            (Value::Float(a), Value::Float(b)) => Ok(rc_cell(Value::Float(a - b))),
+            (Value::Int(a), Value::Int(b)) if b >= 0 => u32::try_from(b)
+                .ok()
+                .and_then(|b| a.checked_pow(b))
+                .map(|v| rc_cell(Value::Int(v)))
+                .ok_or_else(|| {
+                    RuntimeError::new(format!(
+                        "[pow] Integer overflow in {} to the power {}",
+                        a, b
+                    ))
+                }),
+            // A negative power of an integer is a fraction
+            (Value::Int(a), Value::Int(b)) => Ok(rc_cell(Value::Float((a as f64).powf(b as f64)))),
+            (Value::Float(a), Value::Float(b)) => Ok(rc_cell(Value::Float(a.powf(b)))),
             (a, b) => Err(RuntimeError::new(format!(
-                "[sub] Expected two numbers, but got {} and {}",
+                "[pow] Expected two numbers, but got {} and {}",
                 a, b
             ))),
         }
     })?;
-    scope.insert_functor(&string_list!("mul"), |scope| {
-        match (
-            &*scope.query_value(&string_list!("A"))?.borrow(),
-            &*scope.query_value(&string_list!("B"))?.borrow(),
-        ) {
-            (Value::Int(a), Value::Int(b)) => Ok(rc_cell(Value::Int(a * b))),
-            (Value::Float(a), Value::Float(b)) => Ok(rc_cell(Value::Float(a * b))),
-            (a, b) => Err(RuntimeError::new(format!(
-                "[mul] Expected two numbers, but got {} and {}",
-                a, b
-            ))),
+    unary_math(scope, "abs", i64::checked_abs, f64::abs)?;
+    unary_math(scope, "neg", i64::checked_neg, |v| -v)?;
+    scope.insert_functor(&string_list!("min"), |scope| {
+        let a = scope.query_value(&string_list!("A"))?;
+        let b = scope.query_value(&string_list!("B"))?;
+        let ordering = compare("min", &a.borrow(), &b.borrow())?;
+        match ordering {
+            std::cmp::Ordering::Greater => Ok(b),
+            _ => Ok(a),
         }
     })?;
-    scope.insert_functor(&string_list!("div"), |scope| {
-        match (
-            &*scope.query_value(&string_list!("A"))?.borrow(),
-            &*scope.query_value(&string_list!("B"))?.borrow(),
-        ) {
-            (Value::Int(a), Value::Int(b)) => Ok(rc_cell(Value::Int(a / b))),
-            (Value::Float(a), Value::Float(b)) => Ok(rc_cell(Value::Float(a / b))),
-            (a, b) => Err(RuntimeError::new(format!(
-                "[div] Expected two numbers, but got {} and {}",
-                a, b
-            ))),
+    scope.insert_functor(&string_list!("max"), |scope| {
+        let a = scope.query_value(&string_list!("A"))?;
+        let b = scope.query_value(&string_list!("B"))?;
+        let ordering = compare("max", &a.borrow(), &b.borrow())?;
+        match ordering {
+            std::cmp::Ordering::Less => Ok(b),
+            _ => Ok(a),
         }
     })?;
+    rounding(scope, "floor", f64::floor)?;
+    rounding(scope, "ceil", f64::ceil)?;
+    rounding(scope, "round", f64::round)?;
     scope.insert_functor(&string_list!("eq"), |scope| {
-        match (
-            &*scope.query_value(&string_list!("A"))?.borrow(),
-            &*scope.query_value(&string_list!("B"))?.borrow(),
-        ) {
-            (a, b) => Ok(rc_cell(Value::Bool(a == b))),
-        }
+        let a = scope.query_value(&string_list!("A"))?;
+        let b = scope.query_value(&string_list!("B"))?;
+        let (a, b) = promote(&a.borrow(), &b.borrow());
+        Ok(rc_cell(Value::Bool(a == b)))
     })?;
     scope.insert_functor(&string_list!("ne"), |scope| {
-        match (
-            &*scope.query_value(&string_list!("A"))?.borrow(),
-            &*scope.query_value(&string_list!("B"))?.borrow(),
-        ) {
-            (a, b) => Ok(rc_cell(Value::Bool(a != b))),
-        }
-    })?;
-    scope.insert_functor(&string_list!("lt"), |scope| {
-        match (
-            &*scope.query_value(&string_list!("A"))?.borrow(),
-            &*scope.query_value(&string_list!("B"))?.borrow(),
-        ) {
-            (a, b) => Ok(rc_cell(Value::Bool(a < b))),
-        }
-    })?;
-    scope.insert_functor(&string_list!("le"), |scope| {
-        match (
-            &*scope.query_value(&string_list!("A"))?.borrow(),
-            &*scope.query_value(&string_list!("B"))?.borrow(),
-        ) {
-            (a, b) => Ok(rc_cell(Value::Bool(a <= b))),
-        }
-    })?;
-    scope.insert_functor(&string_list!("gt"), |scope| {
-        match (
-            &*scope.query_value(&string_list!("A"))?.borrow(),
-            &*scope.query_value(&string_list!("B"))?.borrow(),
-        ) {
-            (a, b) => Ok(rc_cell(Value::Bool(a > b))),
-        }
-    })?;
-    scope.insert_functor(&string_list!("ge"), |scope| {
-        match (
-            &*scope.query_value(&string_list!("A"))?.borrow(),
-            &*scope.query_value(&string_list!("B"))?.borrow(),
-        ) {
-            (a, b) => Ok(rc_cell(Value::Bool(a >= b))),
-        }
+        let a = scope.query_value(&string_list!("A"))?;
+        let b = scope.query_value(&string_list!("B"))?;
+        let (a, b) = promote(&a.borrow(), &b.borrow());
+        Ok(rc_cell(Value::Bool(a != b)))
     })?;
+    comparison(scope, "lt", std::cmp::Ordering::is_lt)?;
+    comparison(scope, "le", std::cmp::Ordering::is_le)?;
+    comparison(scope, "gt", std::cmp::Ordering::is_gt)?;
+    comparison(scope, "ge", std::cmp::Ordering::is_ge)?;
     scope.insert_functor(&string_list!("not"), |scope| {
         Ok(rc_cell(Value::Bool(
             !scope.query_value(&string_list!("V"))?.borrow().to_bool(),
@@ -214,9 +371,10 @@ pub fn init_functor(scope: &mut Scope) -> Result<(), RuntimeError> {
     })?;
     scope.insert_functor(&string_list!("size"), |scope| {
         match &*scope.query_value(&string_list!("L"))?.borrow() {
-            Value::List(l) => Ok(rc_cell(Value::Int(l.len() as isize))),
+            Value::List(l) => Ok(rc_cell(Value::Int(l.len() as i64))),
+            Value::Bytes(b) => Ok(rc_cell(Value::Int(b.len() as i64))),
             a => Err(RuntimeError::new(format!(
-                "[size] Expected a list, but got {}",
+                "[size] Expected a list or bytes, but got {}",
                 a,
             ))),
         }
@@ -224,8 +382,9 @@ pub fn init_functor(scope: &mut Scope) -> Result<(), RuntimeError> {
     scope.insert_functor(&string_list!("empty"), |scope| {
         match &*scope.query_value(&string_list!("L"))?.borrow() {
             Value::List(l) => Ok(rc_cell(Value::Bool(l.is_empty()))),
+            Value::Bytes(b) => Ok(rc_cell(Value::Bool(b.is_empty()))),
             a => Err(RuntimeError::new(format!(
-                "[empty] Expected a list, but got {}",
+                "[empty] Expected a list or bytes, but got {}",
                 a,
             ))),
         }
@@ -267,14 +426,203 @@ pub fn init_functor(scope: &mut Scope) -> Result<(), RuntimeError> {
         }
     })?;
 
+    scope.insert_functor(&string_list!("get"), |scope| {
+        match (
+            &*scope.query_value(&string_list!("M"))?.borrow(),
+            &*scope.query_value(&string_list!("K"))?.borrow(),
+        ) {
+            (Value::Map(m), Value::Str(k)) => match m.get(k) {
+                Some(v) => Ok(v.clone()),
+                None => Ok(rc_cell(Value::Null)),
+            },
+            (a, b) => Err(RuntimeError::new(format!(
+                "[get] Expected a map and a string, but got {} and {}",
+                a, b,
+            ))),
+        }
+    })?;
+    scope.insert_functor(&string_list!("set"), |scope| {
+        let m = scope.query_value(&string_list!("M"))?;
+        // K is read before M is borrowed, as both may link to the same value
+        let k = scope.query_value(&string_list!("K"))?.borrow().clone();
+        let v = scope.query_value(&string_list!("V"))?;
+        if Rc::ptr_eq(&m, &v) || v.borrow().holds(m.as_ptr() as *const ()) {
+            return Err(RuntimeError::new(
+                "[set] Cannot set a map as a value of itself".to_string(),
+            ));
+        }
+        let mut m = m.try_borrow_mut().map_err(|_| {
+            RuntimeError::new("[set] The map is in use and cannot be changed".to_string())
+        })?;
+        match (&mut *m, &k, v) {
+            (Value::Map(m), Value::Str(k), v) => {
+                m.insert(k.clone(), v);
+                Ok(rc_cell(Value::Null))
+            }
+            (a, b, c) => Err(RuntimeError::new(format!(
+                "[set] Expected a map, a string and a value, but got {}, {} and {}",
+                a,
+                b,
+                c.borrow(),
+            ))),
+        }
+    })?;
+    scope.insert_functor(&string_list!("has"), |scope| {
+        match (
+            &*scope.query_value(&string_list!("M"))?.borrow(),
+            &*scope.query_value(&string_list!("K"))?.borrow(),
+        ) {
+            (Value::Map(m), Value::Str(k)) => Ok(rc_cell(Value::Bool(m.contains_key(k)))),
+            (a, b) => Err(RuntimeError::new(format!(
+                "[has] Expected a map and a string, but got {} and {}",
+                a, b,
+            ))),
+        }
+    })?;
+    scope.insert_functor(&string_list!("keys"), |scope| {
+        match &*scope.query_value(&string_list!("M"))?.borrow() {
+            Value::Map(m) => Ok(rc_cell(Value::List(
+                m.keys().map(|k| rc_cell(Value::Str(k.clone()))).collect(),
+            ))),
+            a => Err(RuntimeError::new(format!(
+                "[keys] Expected a map, but got {}",
+                a,
+            ))),
+        }
+    })?;
+    scope.insert_functor(&string_list!("remove"), |scope| {
+        let m = scope.query_value(&string_list!("M"))?;
+        let k = scope.query_value(&string_list!("K"))?.borrow().clone();
+        let mut m = m.try_borrow_mut().map_err(|_| {
+            RuntimeError::new("[remove] The map is in use and cannot be changed".to_string())
+        })?;
+        match (&mut *m, &k) {
+            (Value::Map(m), Value::Str(k)) => match m.remove(k) {
+                Some(v) => Ok(v),
+                None => Ok(rc_cell(Value::Null)),
+            },
+            (a, b) => Err(RuntimeError::new(format!(
+                "[remove] Expected a map and a string, but got {} and {}",
+                a, b,
+            ))),
+        }
+    })?;
+
+    // Slices from I up to E, or to the end if E is null
+    scope.insert_functor(&string_list!("slice"), |scope| {
+        let range = |len: usize, i: &Value, e: &Value| match (i, e) {
+            (Value::Int(i), Value::Int(e)) if 0 <= *i && i <= e && *e as usize <= len => {
+                Ok(*i as usize..*e as usize)
+            }
+            (Value::Int(i), Value::Null) if 0 <= *i && *i as usize <= len => Ok(*i as usize..len),
+            (i, e) => Err(RuntimeError::new(format!(
+                "[slice] Range from {} to {} is out of range for length {}",
+                i, e, len
+            ))),
+        };
+        match (
+            &*scope.query_value(&string_list!("L"))?.borrow(),
+            &*scope.query_value(&string_list!("I"))?.borrow(),
+            &*scope.query_value(&string_list!("E"))?.borrow(),
+        ) {
+            (Value::List(l), i, e) => Ok(rc_cell(Value::List(
+                l.range(range(l.len(), i, e)?).cloned().collect(),
+            ))),
+            (Value::Bytes(b), i, e) => Ok(rc_cell(Value::Bytes(b[range(b.len(), i, e)?].to_vec()))),
+            (a, _, _) => Err(RuntimeError::new(format!(
+                "[slice] Expected a list or bytes, but got {}",
+                a,
+            ))),
+        }
+    })?;
+    scope.insert_functor(&string_list!("concat"), |scope| {
+        match (
+            &*scope.query_value(&string_list!("A"))?.borrow(),
+            &*scope.query_value(&string_list!("B"))?.borrow(),
+        ) {
+            (Value::List(a), Value::List(b)) => Ok(rc_cell(Value::List(
+                a.iter().chain(b.iter()).cloned().collect(),
+            ))),
+            (Value::Bytes(a), Value::Bytes(b)) => Ok(rc_cell(Value::Bytes([&a[..], b].concat()))),
+            (Value::Str(a), Value::Str(b)) => Ok(rc_cell(Value::Str(format!("{}{}", a, b)))),
+            (a, b) => Err(RuntimeError::new(format!(
+                "[concat] Expected two lists, bytes or strings, but got {} and {}",
+                a, b,
+            ))),
+        }
+    })?;
+    scope.insert_functor(&string_list!("to_bytes"), |scope| {
+        match &*scope.query_value(&string_list!("V"))?.borrow() {
+            Value::Str(s) => Ok(rc_cell(Value::Bytes(s.as_bytes().to_vec()))),
+            a => Err(RuntimeError::new(format!(
+                "[to_bytes] Expected a string, but got {}",
+                a,
+            ))),
+        }
+    })?;
+    scope.insert_functor(&string_list!("to_str"), |scope| {
+        match &*scope.query_value(&string_list!("V"))?.borrow() {
+            Value::Bytes(b) => match String::from_utf8(b.clone()) {
+                Ok(s) => Ok(rc_cell(Value::Str(s))),
+                Err(_) => Err(RuntimeError::new(format!(
+                    "[to_str] Bytes {} are not valid UTF-8",
+                    encode_hex(b)
+                ))),
+            },
+            a => Err(RuntimeError::new(format!(
+                "[to_str] Expected bytes, but got {}",
+                a,
+            ))),
+        }
+    })?;
+    scope.insert_functor(&string_list!("to_hex"), |scope| {
+        match &*scope.query_value(&string_list!("V"))?.borrow() {
+            Value::Bytes(b) => Ok(rc_cell(Value::Str(encode_hex(b)))),
+            a => Err(RuntimeError::new(format!(
+                "[to_hex] Expected bytes, but got {}",
+                a,
+            ))),
+        }
+    })?;
+    scope.insert_functor(&string_list!("from_hex"), |scope| {
+        match &*scope.query_value(&string_list!("V"))?.borrow() {
+            Value::Str(s) => match decode_hex(s) {
+                Ok(b) => Ok(rc_cell(Value::Bytes(b))),
+                Err(e) => Err(RuntimeError::new(format!("[from_hex] {}", e))),
+            },
+            a => Err(RuntimeError::new(format!(
+                "[from_hex] Expected a string, but got {}",
+                a,
+            ))),
+        }
+    })?;
+    scope.insert_functor(&string_list!("to_base64"), |scope| {
+        match &*scope.query_value(&string_list!("V"))?.borrow() {
+            Value::Bytes(b) => Ok(rc_cell(Value::Str(encode_base64(b)))),
+            a => Err(RuntimeError::new(format!(
+                "[to_base64] Expected bytes, but got {}",
+                a,
+            ))),
+        }
+    })?;
+    scope.insert_functor(&string_list!("from_base64"), |scope| {
+        match &*scope.query_value(&string_list!("V"))?.borrow() {
+            Value::Str(s) => match decode_base64(s) {
+                Ok(b) => Ok(rc_cell(Value::Bytes(b))),
+                Err(e) => Err(RuntimeError::new(format!("[from_base64] {}", e))),
+            },
+            a => Err(RuntimeError::new(format!(
+                "[from_base64] Expected a string, but got {}",
+                a,
+            ))),
+        }
+    })?;
+
     scope.insert_functor(&string_list!("mode"), |scope| {
         let mut mode: ScopeMode = Default::default();
         for (k, v) in scope.get_values().iter() {
-            match k.as_str() {
-                "filter" => {
-                    mode.filter = v.borrow().to_bool();
-                }
-                _ => {}
+            if k == "filter" {
+                mode.filter = v.borrow().to_bool();
             }
         }
         if let Some(parent) = scope.get_parent() {
@@ -1,9 +1,21 @@
+#![allow(clippy::module_inception)]
+
+pub mod convert;
+pub mod cst;
 pub mod display;
 pub mod error;
 pub mod init;
 pub mod parser;
 pub mod prelude;
 pub mod scope;
+#[cfg(feature = "serde")]
+pub mod serial;
 pub mod value;
 
-pub use prelude::*;
\ No newline at end of file
+pub use prelude::*;
+
+// Used by the code of the derive macros, and not meant to be used directly
+#[doc(hidden)]
+pub mod __private {
+    pub use crate::convert::field::*;
+}
@@ -1,74 +1,165 @@
-fn main() {
-    use ipml::*;
+use ipml::*;
+use std::process::ExitCode;
 
-    let str = std::fs::read_to_string("examples/example.ipml").unwrap();
-
-    // Parse the file, returning one token
-    let mut parser = Parser::new(str);
-    let token;
-    match parser.parse() {
-        Ok(token_) => {
-            token = token_;
-        }
-        Err(e) => {
-            println!("ERROR! {}", e);
-            panic!();
-        }
-    }
+const USAGE: &str = "Usage:
+    ipml run <file>                 Run a file and print the resulting scope
+    ipml fmt [--check] [files...]   Format files in place, or stdin to stdout if none given
+                                    With --check, only list files that are not formatted
+    ipml convert --to json [file]   Run a file, or stdin if none given, and print the scope as JSON
+    ipml convert --from json [file] Print a JSON object, from a file or stdin, as IPML source";
 
+fn run(path: &str) -> ExitCode {
     // Initialize the scope with default functor
     let mut scope = Scope::new();
     init_functor(&mut scope).unwrap();
 
-    // Feed the parsed token to the scope
-    let result = scope.feed(&token);
+    // Import the file into the scope, so that files it imports are found relative to it
+    let result = scope.import(path);
     // Do cleanup(if necessary)
     scope.cleanup();
 
-    match result {
-        Ok(_) => {}
-        Err(e) => {
-            println!("ERROR! {}", e);
-            panic!();
-        }
+    if let Err(e) = result {
+        eprintln!("ERROR! {}", e);
+        return ExitCode::FAILURE;
     }
     // Output the result
     println!("Resulting Scope: {}", scope);
+    ExitCode::SUCCESS
+}
 
-    // Query one single value
-    let foo = scope.query_value(&string_list!("foo")).unwrap();
-    // foo: Rc<RefCell<Value>>
-    println!("foo: {}", foo.borrow());
+fn fmt(args: &[String]) -> ExitCode {
+    let check = args.iter().any(|arg| arg == "--check");
+    let paths: Vec<&String> = args.iter().filter(|arg| *arg != "--check").collect();
 
-    // Query one single sub-scope
-    let my_scope = scope.query_scope(&string_list!("my_scope")).unwrap();
-    // my_scope: Rc<RefCell<Scope>>
-    println!("my_scope: {}", my_scope.borrow());
+    if paths.is_empty() {
+        let mut str = String::new();
+        if let Err(e) = std::io::Read::read_to_string(&mut std::io::stdin(), &mut str) {
+            eprintln!("ERROR! Cannot read stdin: {}", e);
+            return ExitCode::FAILURE;
+        }
+        return match format_source(&str) {
+            Ok(formatted) if check => {
+                if formatted == str {
+                    ExitCode::SUCCESS
+                } else {
+                    println!("<stdin>");
+                    ExitCode::FAILURE
+                }
+            }
+            Ok(formatted) => {
+                print!("{}", formatted);
+                ExitCode::SUCCESS
+            }
+            Err(e) => {
+                eprintln!("ERROR! {}", e);
+                ExitCode::FAILURE
+            }
+        };
+    }
 
-    // You can also simplify deeply nested queries using string_list!
-    // This is the same as you would write "my_scope.bar"
-    let bar = scope.query_value(&string_list!("my_scope", "bar")).unwrap();
-    // bar: Rc<RefCell<Value>>
-    println!("bar: {}", bar.borrow());
+    let mut code = ExitCode::SUCCESS;
+    for path in paths {
+        let str = match std::fs::read_to_string(path) {
+            Ok(str) => str,
+            Err(e) => {
+                eprintln!("ERROR! Cannot read {}: {}", path, e);
+                code = ExitCode::FAILURE;
+                continue;
+            }
+        };
+        let formatted = match format_source(&str) {
+            Ok(formatted) => formatted,
+            Err(e) => {
+                eprintln!("ERROR! In {}: {}", path, e);
+                code = ExitCode::FAILURE;
+                continue;
+            }
+        };
+        if formatted == str {
+            continue;
+        }
+        if check {
+            println!("{}", path);
+            code = ExitCode::FAILURE;
+        } else if let Err(e) = std::fs::write(path, formatted) {
+            eprintln!("ERROR! Cannot write {}: {}", path, e);
+            code = ExitCode::FAILURE;
+        }
+    }
+    code
+}
 
-    // You can also set values using simple functions
-    scope
-        .set_value(
-            &string_list!("my_scope", "bar"),
-            // rc_cell is a shortcut function that creates a Rc<RefCell<T>>
-            rc_cell(Value::Str("Hello, world!".to_string())),
-        )
-        // Don't actually write unwrap() in your code, please add proper error handling
-        .unwrap();
+fn read_stdin() -> Result<String, String> {
+    let mut str = String::new();
+    std::io::Read::read_to_string(&mut std::io::stdin(), &mut str)
+        .map_err(|e| format!("Cannot read stdin: {}", e))?;
+    Ok(str)
+}
 
-    // You can create sub-scopes using Scope::with_parent, of course, that is empty
-    let my_scope2 = Scope::with_parent("my_scope2".to_string(), &mut scope).unwrap();
-    // my_scope2: Rc<RefCell<Scope>>
-    my_scope2
-        .borrow_mut()
-        .set_value(
-            &string_list!("bar"),
-            rc_cell(Value::Str("This is another message!".to_string())),
-        )
-        .unwrap();
+// Runs IPML source, importing a file so that files it imports are found relative to it
+fn to_json(path: Option<&String>) -> Result<String, String> {
+    let mut scope = Scope::new();
+    init_functor(&mut scope).map_err(|e| e.to_string())?;
+    let result = match path {
+        Some(path) => scope.import(path),
+        None => Parser::new(&read_stdin()?)
+            .parse()
+            .map_err(RuntimeError::from)
+            .and_then(|tokens| scope.feed(&tokens)),
+    };
+    scope.cleanup();
+    result.map_err(|e| e.to_string())?;
+    scope.to_json().map_err(|e| e.to_string())
+}
+
+fn from_json(path: Option<&String>) -> Result<String, String> {
+    let str = match path {
+        Some(path) => {
+            std::fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}", path, e))?
+        }
+        None => read_stdin()?,
+    };
+    let scope = Scope::from_json(&str).map_err(|e| e.to_string())?;
+    let source = scope.borrow().to_ipml_string();
+    source.map_err(|e| e.to_string())
+}
+
+fn convert(args: &[String]) -> ExitCode {
+    let result = match args {
+        [flag, format, rest @ ..] if format == "json" && rest.len() <= 1 => match flag.as_str() {
+            "--to" => to_json(rest.first()),
+            "--from" => from_json(rest.first()),
+            _ => {
+                eprintln!("{}", USAGE);
+                return ExitCode::FAILURE;
+            }
+        },
+        _ => {
+            eprintln!("{}", USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+    match result {
+        Ok(out) => {
+            print!("{}", out);
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("ERROR! {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(|arg| arg.as_str()) {
+        Some("run") if args.len() == 2 => run(&args[1]),
+        Some("fmt") => fmt(&args[1..]),
+        Some("convert") => convert(&args[1..]),
+        _ => {
+            eprintln!("{}", USAGE);
+            ExitCode::FAILURE
+        }
+    }
 }
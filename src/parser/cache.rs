@@ -0,0 +1,365 @@
+use crate::prelude::*;
+use std::path::Path;
+
+// Every cache starts with these bytes, followed by the format version
+const MAGIC: &[u8; 4] = b"IPMC";
+// Bump whenever the layout below changes, so older caches are parsed again
+const VERSION: u16 = 5;
+
+// FNV-1a, which is enough to notice that the source or the syntax it is parsed with changed
+fn checksum(source: &str, syntax: &Syntax) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in format!("{:?}", syntax).bytes().chain(source.bytes()) {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+struct Writer {
+    out: Vec<u8>,
+}
+
+impl Writer {
+    // Unsigned LEB128
+    fn uint(&mut self, mut n: u64) {
+        loop {
+            let byte = (n & 0x7f) as u8;
+            n >>= 7;
+            if n == 0 {
+                self.out.push(byte);
+                break;
+            }
+            self.out.push(byte | 0x80);
+        }
+    }
+
+    fn str(&mut self, s: &str) {
+        self.uint(s.len() as u64);
+        self.out.extend_from_slice(s.as_bytes());
+    }
+
+    fn path(&mut self, path: &[String]) {
+        self.uint(path.len() as u64);
+        for name in path {
+            self.str(name);
+        }
+    }
+
+    fn value(&mut self, value: &Value) -> Result<(), RuntimeError> {
+        match value {
+            Value::Int(i) => {
+                self.out.push(0);
+                self.out.extend_from_slice(&i.to_le_bytes());
+            }
+            Value::Float(f) => {
+                self.out.push(1);
+                self.out.extend_from_slice(&f.to_bits().to_le_bytes());
+            }
+            Value::Bool(b) => self.out.extend_from_slice(&[2, *b as u8]),
+            Value::Str(s) => {
+                self.out.push(3);
+                self.str(s);
+            }
+            Value::List(list) => {
+                self.out.push(4);
+                self.uint(list.len() as u64);
+                for value in list {
+                    self.value(&value.borrow())?;
+                }
+            }
+            Value::Null => self.out.push(5),
+            Value::Map(map) => {
+                self.out.push(6);
+                self.uint(map.len() as u64);
+                for (key, value) in map.iter() {
+                    self.str(key);
+                    self.value(&value.borrow())?;
+                }
+            }
+            Value::Bytes(bytes) => {
+                self.out.push(7);
+                self.uint(bytes.len() as u64);
+                self.out.extend_from_slice(bytes);
+            }
+            Value::Duration(d) => {
+                self.out.push(8);
+                self.uint(d.as_secs());
+                self.uint(d.subsec_nanos() as u64);
+            }
+            Value::Size(s) => {
+                self.out.push(9);
+                self.uint(*s);
+            }
+            Value::Scope(_) | Value::Functor(_) => {
+                return Err(RuntimeError::new(format!(
+                    "[Cache] {} cannot be written to a cache",
+                    value
+                )))
+            }
+        }
+        Ok(())
+    }
+
+    fn decor(&mut self, decor: &BlockDecorator) {
+        match decor {
+            BlockDecorator::None => self.out.push(0),
+            BlockDecorator::Functor => self.out.push(1),
+            BlockDecorator::SubScope => self.out.push(2),
+            BlockDecorator::IndepScope => self.out.push(3),
+            BlockDecorator::Custom(keyword) => {
+                self.out.push(4);
+                self.str(keyword);
+            }
+            BlockDecorator::Map => self.out.push(5),
+        }
+    }
+
+    // The file of a span is left out, as it is given when the cache is read
+    fn span(&mut self, span: &Option<Span>) {
+        match span {
+            Some(span) => {
+                self.out.push(1);
+                for n in [span.start, span.end, span.line, span.column] {
+                    self.uint(n as u64);
+                }
+            }
+            None => self.out.push(0),
+        }
+    }
+
+    fn token(&mut self, token: &Token) -> Result<(), RuntimeError> {
+        match token {
+            Token::Value(value) => {
+                self.out.push(0);
+                self.value(value)?;
+            }
+            Token::Symbol(path) => {
+                self.out.push(1);
+                self.path(path);
+            }
+            Token::Tag(path) => {
+                self.out.push(2);
+                self.path(path);
+            }
+            Token::Block(block) => {
+                self.out.push(3);
+                self.decor(block.decor());
+                self.uint(block.len() as u64);
+                for (token, span) in block.data().iter().zip(block.spans()) {
+                    self.token(token)?;
+                    self.span(span);
+                }
+            }
+            Token::Operator(op) => {
+                self.out.push(4);
+                self.str(op);
+            }
+            Token::Decorator(decor) => {
+                self.out.push(5);
+                self.decor(decor);
+            }
+        }
+        Ok(())
+    }
+}
+
+// Every read returns None once the input runs out or holds something unexpected
+struct Reader<'a> {
+    bytes: &'a [u8],
+    file: usize,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, n: usize) -> Option<&[u8]> {
+        if n > self.bytes.len() {
+            return None;
+        }
+        let (taken, rest) = self.bytes.split_at(n);
+        self.bytes = rest;
+        Some(taken)
+    }
+
+    fn byte(&mut self) -> Option<u8> {
+        self.take(1).map(|bytes| bytes[0])
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_le_bytes(self.take(8)?.try_into().ok()?))
+    }
+
+    fn uint(&mut self) -> Option<u64> {
+        let mut n: u64 = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            n |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Some(n);
+            }
+        }
+        None
+    }
+
+    fn len(&mut self) -> Option<usize> {
+        let len = self.uint()? as usize;
+        // Each item takes at least one byte, which keeps a corrupted length from allocating
+        if len > self.bytes.len() {
+            None
+        } else {
+            Some(len)
+        }
+    }
+
+    fn str(&mut self) -> Option<String> {
+        let len = self.len()?;
+        String::from_utf8(self.take(len)?.to_vec()).ok()
+    }
+
+    fn path(&mut self) -> Option<Vec<String>> {
+        let len = self.len()?;
+        (0..len).map(|_| self.str()).collect()
+    }
+
+    fn value(&mut self) -> Option<Value> {
+        Some(match self.byte()? {
+            0 => Value::Int(self.u64()? as i64),
+            1 => Value::Float(f64::from_bits(self.u64()?)),
+            2 => Value::Bool(self.byte()? != 0),
+            3 => Value::Str(self.str()?),
+            4 => {
+                let len = self.len()?;
+                let mut list = VecDeque::with_capacity(len);
+                for _ in 0..len {
+                    list.push_back(rc_cell(self.value()?));
+                }
+                Value::List(list)
+            }
+            5 => Value::Null,
+            6 => {
+                let len = self.len()?;
+                let mut map = Map::new();
+                for _ in 0..len {
+                    let key = self.str()?;
+                    map.insert(key, rc_cell(self.value()?));
+                }
+                Value::Map(map)
+            }
+            7 => {
+                let len = self.len()?;
+                Value::Bytes(self.take(len)?.to_vec())
+            }
+            8 => {
+                let secs = self.uint()?;
+                let nanos = u32::try_from(self.uint()?)
+                    .ok()
+                    .filter(|n| *n < 1_000_000_000)?;
+                Value::Duration(std::time::Duration::new(secs, nanos))
+            }
+            9 => Value::Size(self.uint()?),
+            _ => return None,
+        })
+    }
+
+    fn decor(&mut self) -> Option<BlockDecorator> {
+        Some(match self.byte()? {
+            0 => BlockDecorator::None,
+            1 => BlockDecorator::Functor,
+            2 => BlockDecorator::SubScope,
+            3 => BlockDecorator::IndepScope,
+            4 => BlockDecorator::Custom(self.str()?),
+            5 => BlockDecorator::Map,
+            _ => return None,
+        })
+    }
+
+    fn span(&mut self) -> Option<Option<Span>> {
+        match self.byte()? {
+            0 => Some(None),
+            1 => {
+                let mut n = [0; 4];
+                for n in n.iter_mut() {
+                    *n = self.uint()? as usize;
+                }
+                Some(Some(Span::new(self.file, n[0], n[1], n[2], n[3])))
+            }
+            _ => None,
+        }
+    }
+
+    fn token(&mut self) -> Option<Token> {
+        Some(match self.byte()? {
+            0 => Token::Value(self.value()?),
+            1 => Token::Symbol(self.path()?),
+            2 => Token::Tag(self.path()?),
+            3 => {
+                let mut block = BlockVec::new();
+                block.set_decor(self.decor()?);
+                for _ in 0..self.len()? {
+                    let token = self.token()?;
+                    match self.span()? {
+                        Some(span) => block.push_spanned(token, span),
+                        None => block.push(token),
+                    }
+                }
+                Token::Block(block)
+            }
+            4 => Token::Operator(self.str()?),
+            5 => Token::Decorator(self.decor()?),
+            _ => return None,
+        })
+    }
+}
+
+/// Serializes a parsed token tree, along with a checksum of the source and syntax it was
+/// parsed with
+pub fn write_cache(token: &Token, source: &str, syntax: &Syntax) -> Result<Vec<u8>, RuntimeError> {
+    let mut writer = Writer { out: Vec::new() };
+    writer.out.extend_from_slice(MAGIC);
+    writer.out.extend_from_slice(&VERSION.to_le_bytes());
+    writer
+        .out
+        .extend_from_slice(&checksum(source, syntax).to_le_bytes());
+    writer.token(token)?;
+    Ok(writer.out)
+}
+
+/// Loads a token tree written by write_cache, giving its spans the file id, or None if the
+/// cache is not of this version, was written for another source or syntax, or is corrupted
+pub fn read_cache(bytes: &[u8], source: &str, syntax: &Syntax, file: usize) -> Option<Token> {
+    let mut reader = Reader { bytes, file };
+    if reader.take(4)? != MAGIC
+        || u16::from_le_bytes(reader.take(2)?.try_into().ok()?) != VERSION
+        || reader.u64()? != checksum(source, syntax)
+    {
+        return None;
+    }
+    let token = reader.token()?;
+    if reader.bytes.is_empty() {
+        Some(token)
+    } else {
+        None
+    }
+}
+
+/// Parses source with the syntax, loading the result from the cache at the path if it is up
+/// to date, and otherwise writing it there for next time
+pub fn parse_cached(
+    source: String,
+    file: usize,
+    syntax: &Syntax,
+    cache: &Path,
+) -> Result<Token, SyntaxError> {
+    if let Ok(bytes) = std::fs::read(cache) {
+        if let Some(token) = read_cache(&bytes, &source, syntax, file) {
+            return Ok(token);
+        }
+    }
+    let mut parser = Parser::with_file(source.clone(), file);
+    parser.set_syntax(syntax.clone());
+    let token = parser.parse()?;
+    // Failing to write the cache only means parsing again next time
+    if let Ok(bytes) = write_cache(&token, &source, syntax) {
+        let _ = std::fs::write(cache, bytes);
+    }
+    Ok(token)
+}
@@ -0,0 +1,297 @@
+use crate::prelude::*;
+
+// Arrays and objects nested deeper are rejected, so reading them cannot overflow the stack
+const MAX_DEPTH: usize = 128;
+
+// Reads JSON into values, objects of an object becoming its sub-scopes
+struct JsonReader {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+    column: usize,
+    // Arrays and objects the reader is in
+    depth: usize,
+}
+
+impl JsonReader {
+    fn error(&self, msg: String) -> RuntimeError {
+        RuntimeError::new(format!(
+            "[Json] {} at line {}, column {}",
+            msg, self.line, self.column
+        ))
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn enter(&mut self) -> Result<(), RuntimeError> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(self.error(format!("Nesting deeper than {} levels", MAX_DEPTH)));
+        }
+        Ok(())
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t' | '\n' | '\r')) {
+            self.next();
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), RuntimeError> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(c) if c == expected => {
+                self.next();
+                Ok(())
+            }
+            Some(c) => Err(self.error(format!(
+                "Expected {}, but got {}",
+                expected,
+                c.escape_debug()
+            ))),
+            None => Err(self.error(format!("Expected {}, but got the end", expected))),
+        }
+    }
+
+    fn keyword(&mut self, word: &str, value: Value) -> Result<Value, RuntimeError> {
+        for expected in word.chars() {
+            if self.peek() != Some(expected) {
+                return Err(self.error(format!("Expected {}", word)));
+            }
+            self.next();
+        }
+        Ok(value)
+    }
+
+    fn hex4(&mut self) -> Result<u32, RuntimeError> {
+        let mut n = 0;
+        for _ in 0..4 {
+            match self.next().and_then(|c| c.to_digit(16)) {
+                Some(d) => n = n << 4 | d,
+                None => return Err(self.error("Expected 4 hex digits after \\u".to_string())),
+            }
+        }
+        Ok(n)
+    }
+
+    fn string(&mut self) -> Result<String, RuntimeError> {
+        self.expect('"')?;
+        let mut out = String::new();
+        loop {
+            match self.next() {
+                None => return Err(self.error("Unterminated string".to_string())),
+                Some('"') => return Ok(out),
+                Some('\\') => match self.next() {
+                    Some('"') => out.push('"'),
+                    Some('\\') => out.push('\\'),
+                    Some('/') => out.push('/'),
+                    Some('b') => out.push('\u{8}'),
+                    Some('f') => out.push('\u{c}'),
+                    Some('n') => out.push('\n'),
+                    Some('r') => out.push('\r'),
+                    Some('t') => out.push('\t'),
+                    Some('u') => {
+                        let mut code = self.hex4()?;
+                        // A high surrogate is followed by the low one of the pair
+                        if (0xd800..0xdc00).contains(&code) {
+                            if self.next() != Some('\\') || self.next() != Some('u') {
+                                return Err(self.error("Expected a low surrogate".to_string()));
+                            }
+                            let low = self.hex4()?;
+                            if !(0xdc00..0xe000).contains(&low) {
+                                return Err(self.error("Expected a low surrogate".to_string()));
+                            }
+                            code = 0x10000 + ((code - 0xd800) << 10) + (low - 0xdc00);
+                        }
+                        match char::from_u32(code) {
+                            Some(c) => out.push(c),
+                            None => {
+                                return Err(self.error(format!("Invalid unicode escape {:x}", code)))
+                            }
+                        }
+                    }
+                    Some(c) => return Err(self.error(format!("Invalid escape \\{}", c))),
+                    None => return Err(self.error("Unterminated string".to_string())),
+                },
+                Some(c) if (c as u32) < 0x20 => {
+                    return Err(
+                        self.error(format!("Control character {} in string", c.escape_debug()))
+                    )
+                }
+                Some(c) => out.push(c),
+            }
+        }
+    }
+
+    fn digits(&mut self, out: &mut String) -> Result<(), RuntimeError> {
+        let start = out.len();
+        while let Some(c) = self.peek().filter(|c| c.is_ascii_digit()) {
+            out.push(c);
+            self.next();
+        }
+        if out.len() == start {
+            return Err(self.error("Expected a digit".to_string()));
+        }
+        Ok(())
+    }
+
+    // Whole numbers that fit are ints, the rest floats
+    fn number(&mut self) -> Result<Value, RuntimeError> {
+        let mut num = String::new();
+        if self.peek() == Some('-') {
+            num.push('-');
+            self.next();
+        }
+        let int_start = num.len();
+        self.digits(&mut num)?;
+        if num[int_start..].starts_with('0') && num.len() - int_start > 1 {
+            return Err(self.error("Leading zeros are not allowed".to_string()));
+        }
+        let mut float = false;
+        if self.peek() == Some('.') {
+            float = true;
+            num.push('.');
+            self.next();
+            self.digits(&mut num)?;
+        }
+        if let Some(e @ ('e' | 'E')) = self.peek() {
+            float = true;
+            num.push(e);
+            self.next();
+            if let Some(sign @ ('+' | '-')) = self.peek() {
+                num.push(sign);
+                self.next();
+            }
+            self.digits(&mut num)?;
+        }
+        if !float {
+            if let Ok(i) = num.parse::<i64>() {
+                return Ok(Value::Int(i));
+            }
+        }
+        match num.parse::<f64>() {
+            Ok(f) if f.is_finite() => Ok(Value::Float(f)),
+            _ => Err(self.error(format!("Number {} is out of range", num))),
+        }
+    }
+
+    // Reads the members of an object into the scope
+    fn object(&mut self, scope: &mut Scope) -> Result<(), RuntimeError> {
+        self.expect('{')?;
+        self.enter()?;
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.next();
+            self.depth -= 1;
+            return Ok(());
+        }
+        loop {
+            self.skip_whitespace();
+            let name = [self.string()?];
+            // IPML has no way to write an empty name
+            if name[0].is_empty() {
+                return Err(self.error("Empty keys are not supported".to_string()));
+            }
+            self.expect(':')?;
+            self.skip_whitespace();
+            scope.remove_entry(&name[0]);
+            if self.peek() == Some('{') {
+                let child = rc_cell(Scope::new());
+                self.object(&mut child.borrow_mut())?;
+                scope.set_scope(&name, child)?;
+            } else {
+                let value = self.value()?;
+                scope.set_value(&name, rc_cell(value))?;
+            }
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => continue,
+                Some('}') => {
+                    self.depth -= 1;
+                    return Ok(());
+                }
+                _ => return Err(self.error("Expected , or } after a member".to_string())),
+            }
+        }
+    }
+
+    // Objects that are not members of an object are scope values
+    fn value(&mut self) -> Result<Value, RuntimeError> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('{') => {
+                let scope = rc_cell(Scope::new());
+                self.object(&mut scope.borrow_mut())?;
+                Ok(Value::Scope(scope))
+            }
+            Some('[') => {
+                self.next();
+                self.enter()?;
+                let mut list = VecDeque::new();
+                self.skip_whitespace();
+                if self.peek() == Some(']') {
+                    self.next();
+                    self.depth -= 1;
+                    return Ok(Value::List(list));
+                }
+                loop {
+                    list.push_back(rc_cell(self.value()?));
+                    self.skip_whitespace();
+                    match self.next() {
+                        Some(',') => continue,
+                        Some(']') => {
+                            self.depth -= 1;
+                            return Ok(Value::List(list));
+                        }
+                        _ => return Err(self.error("Expected , or ] after an item".to_string())),
+                    }
+                }
+            }
+            Some('"') => Ok(Value::Str(self.string()?)),
+            Some('t') => self.keyword("true", Value::Bool(true)),
+            Some('f') => self.keyword("false", Value::Bool(false)),
+            Some('n') => self.keyword("null", Value::Null),
+            Some('-' | '0'..='9') => self.number(),
+            Some(c) => Err(self.error(format!("Unexpected {}", c.escape_debug()))),
+            None => Err(self.error("Expected a value, but got the end".to_string())),
+        }
+    }
+}
+
+impl Scope {
+    /// Reads a JSON object into a new scope, with objects it holds as sub-scopes
+    ///
+    /// Objects inside arrays become scope values, numbers with no fraction or exponent that
+    /// fit are ints and the rest floats, and a repeated key takes the last value. Empty keys
+    /// and nesting deeper than 128 arrays and objects are rejected
+    pub fn from_json(str: &str) -> Result<ScopeRc, RuntimeError> {
+        let mut reader = JsonReader {
+            chars: str.chars().collect(),
+            pos: 0,
+            line: 1,
+            column: 1,
+            depth: 0,
+        };
+        let scope = rc_cell(Scope::new());
+        reader.object(&mut scope.borrow_mut())?;
+        reader.skip_whitespace();
+        if reader.peek().is_some() {
+            return Err(reader.error("Unexpected content after the object".to_string()));
+        }
+        Ok(scope)
+    }
+}
@@ -0,0 +1,226 @@
+use super::tokenize::{parse_slice, unescape, Nested};
+use super::*;
+use crate::prelude::*;
+use std::borrow::Cow;
+
+/// A token borrowing its text from the source, only allocating for strings with escapes
+#[derive(Debug, Clone)]
+pub enum TokenRef<'src> {
+    // Any literal but a string
+    Value(Value),
+    Str(Cow<'src, str>),
+    Symbol(PathRef<'src>),
+    Tag(PathRef<'src>),
+    Block(BlockRef<'src>),
+    Operator(&'src str),
+    Decorator(BlockDecorator),
+}
+
+impl TokenRef<'_> {
+    // Copies the token and everything it holds, as if it was produced by Parser
+    pub fn to_token(&self) -> Token {
+        match self {
+            TokenRef::Value(value) => Token::Value(value.clone()),
+            TokenRef::Str(str) => Token::Value(Value::Str(str.to_string())),
+            TokenRef::Symbol(path) => Token::Symbol(path.to_vec()),
+            TokenRef::Tag(path) => Token::Tag(path.to_vec()),
+            TokenRef::Block(block) => Token::Block(block.to_block()),
+            TokenRef::Operator(op) => Token::Operator(op.to_string()),
+            TokenRef::Decorator(decor) => Token::Decorator(decor.clone()),
+        }
+    }
+}
+
+/// The tokens of a block, along with their spans
+#[derive(Debug, Clone, Default)]
+pub struct BlockRef<'src> {
+    data: Vec<TokenRef<'src>>,
+    decor: BlockDecorator,
+    spans: Vec<Option<Span>>,
+}
+
+impl<'src> BlockRef<'src> {
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn data(&self) -> &Vec<TokenRef<'src>> {
+        &self.data
+    }
+
+    pub fn decor(&self) -> &BlockDecorator {
+        &self.decor
+    }
+
+    pub fn spans(&self) -> &Vec<Option<Span>> {
+        &self.spans
+    }
+
+    pub fn to_block(&self) -> BlockVec {
+        let mut block = BlockVec::new();
+        block.set_decor(self.decor.clone());
+        for (token, span) in self.data.iter().zip(&self.spans) {
+            Token::push(&mut block, token.to_token(), *span);
+        }
+        block
+    }
+}
+
+impl<'src> Nested for TokenRef<'src> {
+    type Block = BlockRef<'src>;
+
+    fn new_block(decor: BlockDecorator) -> BlockRef<'src> {
+        BlockRef {
+            decor,
+            ..Default::default()
+        }
+    }
+
+    fn push(block: &mut BlockRef<'src>, token: TokenRef<'src>, span: Option<Span>) {
+        block.data.push(token);
+        block.spans.push(span);
+    }
+
+    fn from_block(block: BlockRef<'src>) -> Self {
+        TokenRef::Block(block)
+    }
+
+    fn operator(&self) -> Option<&str> {
+        match self {
+            TokenRef::Operator(op) => Some(op),
+            _ => None,
+        }
+    }
+
+    fn decorator(&self) -> Option<&BlockDecorator> {
+        match self {
+            TokenRef::Decorator(decor) => Some(decor),
+            _ => None,
+        }
+    }
+}
+
+/// A dotted name as written in the source, with quoted sections still quoted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PathRef<'src>(&'src str);
+
+impl<'src> PathRef<'src> {
+    pub(crate) fn new(raw: &'src str) -> Self {
+        Self(raw)
+    }
+
+    pub fn raw(&self) -> &'src str {
+        self.0
+    }
+
+    // The names of the sections, only allocating for quoted ones with escapes
+    pub fn sections(&self) -> Sections<'src> {
+        Sections(Some(self.0))
+    }
+
+    pub fn to_vec(&self) -> Vec<String> {
+        self.sections()
+            .map(|section| section.into_owned())
+            .collect()
+    }
+}
+
+/// Iterates over the sections of a PathRef
+#[derive(Debug, Clone)]
+pub struct Sections<'src>(Option<&'src str>);
+
+impl<'src> Iterator for Sections<'src> {
+    type Item = Cow<'src, str>;
+
+    fn next(&mut self) -> Option<Cow<'src, str>> {
+        let rest = self.0?;
+        let (section, after) = match rest.strip_prefix('`') {
+            Some(quoted) => {
+                // The path was validated, so the closing backtick is the first unescaped one
+                let mut chars = quoted.char_indices();
+                let mut escaped = false;
+                let mut close = quoted.len();
+                while let Some((i, c)) = chars.next() {
+                    if c == '\\' {
+                        chars.next();
+                        escaped = true;
+                    } else if c == '`' {
+                        close = i;
+                        break;
+                    }
+                }
+                let name = &quoted[..close];
+                let section = if escaped {
+                    Cow::Owned(unescape(name))
+                } else {
+                    Cow::Borrowed(name)
+                };
+                (section, quoted.get(close + 1..).unwrap_or(""))
+            }
+            None => {
+                let end = rest.find('.').unwrap_or(rest.len());
+                (Cow::Borrowed(&rest[..end]), &rest[end..])
+            }
+        };
+        self.0 = after.strip_prefix('.');
+        Some(section)
+    }
+}
+
+/// Tokenizes borrowed source without copying it, for input that is only read
+pub struct Lexer<'src> {
+    src: &'src str,
+    parser: Parser<'src>,
+}
+
+impl<'src> Lexer<'src> {
+    pub fn new(src: &'src str) -> Self {
+        Self::with_file(src, 0)
+    }
+
+    pub fn with_file(src: &'src str, file: usize) -> Self {
+        Self {
+            src,
+            parser: Parser::with_file(src, file),
+        }
+    }
+
+    pub fn syntax(&self) -> &Syntax {
+        self.parser.syntax()
+    }
+
+    pub fn set_syntax(&mut self, syntax: Syntax) {
+        self.parser.set_syntax(syntax);
+    }
+
+    pub fn tokenize(&mut self) -> Option<Result<(TokenRef<'src>, Span), SyntaxError>> {
+        let result = self.parser.next_lexeme()?;
+        // The source is never compacted, so ranges of the parser are ranges of src
+        Some(result.map(|(lexeme, span)| (lexeme.into_ref(self.src), span)))
+    }
+
+    pub fn recover(&mut self) {
+        self.parser.recover();
+    }
+
+    pub fn parse(&mut self) -> Result<TokenRef<'src>, SyntaxError> {
+        let mut tokens = Vec::new();
+        let mut spans = Vec::new();
+        while let Some(token) = self.tokenize() {
+            let (token, span) = token?;
+            tokens.push(token);
+            spans.push(Some(span));
+        }
+        let mut block = BlockRef::default();
+        let mut errors = Vec::new();
+        parse_slice(&mut block, &tokens, &spans, None, &mut errors);
+        match errors.into_iter().next() {
+            Some(e) => Err(e),
+            None => Ok(TokenRef::Block(block)),
+        }
+    }
+}
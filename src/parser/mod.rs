@@ -1,5 +1,18 @@
+mod cache;
+mod json;
+mod lexer;
 mod parser;
+mod span;
+mod stream;
+mod syntax;
 mod tokenize;
 
+pub use cache::{parse_cached, read_cache, write_cache};
+pub use lexer::{BlockRef, Lexer, PathRef, Sections, TokenRef};
 pub use parser::Parser;
+pub use span::Span;
+pub use stream::StreamParser;
+pub use syntax::Syntax;
 pub use tokenize::Tokenize;
+
+pub(crate) use tokenize::parse_slice;
@@ -1,53 +1,167 @@
 use crate::prelude::*;
+use std::borrow::Cow;
 
+// The input is either owned or borrowed, in which case it is never copied
 #[derive(Debug, Clone)]
-pub struct Parser {
-    str: String,
+pub struct Parser<'src> {
+    str: Cow<'src, str>,
+    file: usize,
+    // Byte offset of str in the whole input, which grows when consumed input is dropped
+    base: usize,
+    // Byte offset into str, always on a char boundary
     pos: usize,
     line: usize,
-    prev_cursor: usize,
     cursor: usize,
+    // Position, line and cursor before the last call to next_char, used by back
+    prev: (usize, usize, usize),
+    // Beginning of the last token, where recovery restarts after an error
+    last: Span,
+    syntax: Syntax,
 }
 
-impl Parser {
-    pub fn new(str: String) -> Self {
+impl<'src> Parser<'src> {
+    pub fn new(str: impl Into<Cow<'src, str>>) -> Self {
+        Self::with_file(str, 0)
+    }
+
+    // The file id is carried by every span this parser produces
+    pub fn with_file(str: impl Into<Cow<'src, str>>, file: usize) -> Self {
         Self {
-            str,
+            str: str.into(),
+            file,
+            base: 0,
             pos: 0,
             line: 1,
-            prev_cursor: 1,
             cursor: 1,
+            prev: (0, 1, 1),
+            last: Span::new(file, 0, 0, 1, 1),
+            syntax: Syntax::default(),
         }
     }
 
-    pub fn next(&mut self) -> Option<char> {
-        if self.pos < self.str.len() {
-            let c = self.str.chars().nth(self.pos);
-            self.pos += 1;
-
-            self.prev_cursor = self.cursor;
-            if c == Some('\n') {
-                self.line += 1;
-                self.cursor = 1;
-            } else {
-                self.cursor += 1;
-            }
-
-            c
+    pub fn syntax(&self) -> &Syntax {
+        &self.syntax
+    }
+
+    pub fn set_syntax(&mut self, syntax: Syntax) {
+        self.syntax = syntax;
+    }
+
+    pub fn next_char(&mut self) -> Option<char> {
+        let c = self.str[self.pos..].chars().next()?;
+        self.prev = (self.pos, self.line, self.cursor);
+        self.pos += c.len_utf8();
+
+        if c == '\n' {
+            self.line += 1;
+            self.cursor = 1;
         } else {
-            None
+            self.cursor += 1;
         }
+
+        Some(c)
+    }
+
+    pub fn peek_char(&self) -> Option<char> {
+        self.str[self.pos..].chars().next()
     }
 
+    // The names next_char and peek_char had before 0.5, kept so existing callers still build
+    #[deprecated(since = "0.5.0", note = "renamed to next_char")]
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Option<char> {
+        self.next_char()
+    }
+
+    #[deprecated(since = "0.5.0", note = "renamed to peek_char")]
     pub fn peek(&self) -> Option<char> {
-        self.str.chars().nth(self.pos)
+        self.peek_char()
+    }
+
+    // Looks n chars ahead without consuming, peek_nth(0) is the same as peek_char
+    pub fn peek_nth(&self, n: usize) -> Option<char> {
+        self.str[self.pos..].chars().nth(n)
+    }
+
+    pub(crate) fn source(&self) -> &str {
+        &self.str
+    }
+
+    pub(crate) fn pos(&self) -> usize {
+        self.pos
+    }
+
+    // The input that has not been consumed yet
+    pub(crate) fn rest(&self) -> &str {
+        &self.str[self.pos..]
+    }
+
+    // Whether the input continues with r"..." or r#"..."#
+    pub(crate) fn at_raw_string(&self) -> bool {
+        let rest = self.rest();
+        rest.starts_with('r') && rest[1..].trim_start_matches('#').starts_with('"')
+    }
+
+    // The prefix of a bytes literal the input starts with, one of b"...", hex"..." or b64"..."
+    pub(crate) fn at_bytes(&self) -> Option<&'static str> {
+        let rest = self.rest();
+        ["b", "hex", "b64"].into_iter().find(|prefix| {
+            rest.strip_prefix(prefix)
+                .is_some_and(|s| s.starts_with('"'))
+        })
+    }
+
+    // Whether the input continues with word, not followed by any identifier char
+    pub(crate) fn at_word(&self, word: &str) -> bool {
+        let rest = &self.str[self.pos..];
+        rest.starts_with(word)
+            && !matches!(rest[word.len()..].chars().next(), Some(c) if self.syntax.is_ident_continue(c))
+    }
+
+    // Appends more input, used when reading from a stream
+    pub(crate) fn push_str(&mut self, str: &str) {
+        self.str.to_mut().push_str(str);
+    }
+
+    // Drops the consumed input, only to be called between tokens
+    pub(crate) fn compact(&mut self) {
+        self.str.to_mut().drain(..self.pos);
+        self.base += self.pos;
+        self.prev.0 = self.prev.0.saturating_sub(self.pos);
+        self.pos = 0;
     }
 
+    // Steps back over the last char returned by next_char, only one step is remembered
     pub fn back(&mut self) {
-        self.pos -= 1;
-        self.cursor = self.prev_cursor;
-        if self.str.chars().nth(self.pos) == Some('\n') {
-            self.line -= 1;
+        (self.pos, self.line, self.cursor) = self.prev;
+    }
+
+    // Marks the beginning of a token, to be closed by span_from
+    pub(crate) fn mark(&self) -> Span {
+        let pos = self.base + self.pos;
+        Span::new(self.file, pos, pos, self.line, self.cursor)
+    }
+
+    // Moves back to a mark returned earlier
+    pub(crate) fn reset(&mut self, mark: Span) {
+        self.pos = mark.start - self.base;
+        self.line = mark.line;
+        self.cursor = mark.column;
+        self.prev = (self.pos, self.line, self.cursor);
+    }
+
+    pub(crate) fn last_mark(&self) -> Span {
+        self.last
+    }
+
+    pub(crate) fn set_last_mark(&mut self, mark: Span) {
+        self.last = mark;
+    }
+
+    pub(crate) fn span_from(&self, mark: Span) -> Span {
+        Span {
+            end: self.base + self.pos,
+            ..mark
         }
     }
 
@@ -0,0 +1,29 @@
+/// The position of a token in its source, lines and columns are counted from 1
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub file: usize,
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Span {
+    pub fn new(file: usize, start: usize, end: usize, line: usize, column: usize) -> Self {
+        Self {
+            file,
+            start,
+            end,
+            line,
+            column,
+        }
+    }
+
+    // Creates a span covering both self and other, where other comes after self
+    pub fn to(&self, other: &Span) -> Span {
+        Span {
+            end: other.end,
+            ..*self
+        }
+    }
+}
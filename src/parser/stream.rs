@@ -0,0 +1,123 @@
+use super::*;
+use crate::prelude::*;
+use std::io::BufRead;
+
+// Consumed input is dropped once it grows beyond this many bytes
+const COMPACT_THRESHOLD: usize = 64 * 1024;
+
+/// Tokenizes input read from a stream, reading only as much as the next token needs
+pub struct StreamParser<R: BufRead> {
+    reader: R,
+    parser: Parser<'static>,
+    eof: bool,
+}
+
+impl<R: BufRead> StreamParser<R> {
+    pub fn new(reader: R) -> Self {
+        Self::with_file(reader, 0)
+    }
+
+    pub fn with_file(reader: R, file: usize) -> Self {
+        Self {
+            reader,
+            parser: Parser::with_file(String::new(), file),
+            eof: false,
+        }
+    }
+
+    // Reads one more line, never waiting for more input than that, and gives it back unless
+    // the input has ended
+    fn read_line(&mut self) -> Result<Option<String>, SyntaxError> {
+        let mut line = String::new();
+        match self.reader.read_line(&mut line) {
+            Ok(0) => {
+                self.eof = true;
+                Ok(None)
+            }
+            Ok(_) => {
+                self.parser.push_str(&line);
+                Ok(Some(line))
+            }
+            // Nothing more can be read after a failure
+            Err(e) => {
+                self.eof = true;
+                Err(SyntaxError::Io(e))
+            }
+        }
+    }
+}
+
+// The delimiter that closes a token starting at text, for the tokens that may span lines, and
+// whether it may be escaped by a backslash
+fn closing(mut text: &str) -> Option<(String, bool)> {
+    // Skips the whitespace and comments the token follows
+    loop {
+        text = text.trim_start();
+        match text.strip_prefix('#') {
+            Some(comment) => text = comment.split_once('\n').map_or("", |(_, rest)| rest),
+            None => break,
+        }
+    }
+    if text.starts_with("\"\"\"") {
+        return Some(("\"\"\"".to_string(), true));
+    }
+    if ["\"", "b\"", "hex\"", "b64\""]
+        .iter()
+        .any(|open| text.starts_with(open))
+    {
+        return Some(("\"".to_string(), true));
+    }
+    let hashes = text.strip_prefix('r')?;
+    let count = hashes.len() - hashes.trim_start_matches('#').len();
+    hashes[count..]
+        .starts_with('"')
+        .then(|| (format!("\"{}", "#".repeat(count)), false))
+}
+
+// Whether the line holds the delimiter, not escaped by an odd number of backslashes
+fn closes(line: &str, close: &str, escapes: bool) -> bool {
+    line.match_indices(close).any(|(i, _)| {
+        !escapes || (line[..i].len() - line[..i].trim_end_matches('\\').len()).is_multiple_of(2)
+    })
+}
+
+impl<R: BufRead> Tokenize for StreamParser<R> {
+    fn recover(&mut self) {
+        self.parser.recover();
+    }
+
+    fn tokenize(&mut self) -> Option<Result<(Token, Span), SyntaxError>> {
+        if self.parser.pos() > COMPACT_THRESHOLD {
+            self.parser.compact();
+        }
+        loop {
+            // Input is buffered in whole lines, so a token can only be cut short if it runs
+            // into the end of the buffer, in which case it is tokenized again with more input
+            if !self.eof && self.parser.rest().is_empty() {
+                if let Err(e) = self.read_line() {
+                    return Some(Err(e));
+                }
+                continue;
+            }
+            let mark = self.parser.mark();
+            let result = self.parser.tokenize();
+            if self.eof || !self.parser.rest().is_empty() {
+                return result;
+            }
+            self.parser.reset(mark);
+            // Lines that cannot close a string are only buffered, so a long text block is
+            // tokenized again once it may be complete rather than after every line
+            let close = closing(self.parser.rest());
+            loop {
+                match self.read_line() {
+                    Err(e) => return Some(Err(e)),
+                    Ok(Some(line)) => match &close {
+                        Some((close, escapes)) if !closes(&line, close, *escapes) => continue,
+                        _ => break,
+                    },
+                    Ok(None) => break,
+                }
+            }
+        }
+    }
+}
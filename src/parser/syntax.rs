@@ -0,0 +1,88 @@
+/// Rules the parser follows, which hosts may change before parsing
+#[derive(Debug, Clone, PartialEq)]
+pub struct Syntax {
+    // Whether identifiers follow Unicode XID rules, otherwise only ASCII letters are allowed
+    pub unicode_idents: bool,
+    // Chars besides letters that may start an identifier
+    pub ident_start: Vec<char>,
+    // Chars besides letters and digits that may continue an identifier
+    pub ident_continue: Vec<char>,
+    // Operators longer than one char, each starting with a char that cannot start any other
+    // token, single char operators need not be listed
+    pub operators: Vec<String>,
+    // Decorator keywords besides fn, subsc and indep, given meaning by Scope::register_decorator
+    pub decorators: Vec<String>,
+}
+
+impl Default for Syntax {
+    fn default() -> Self {
+        Self {
+            unicode_idents: true,
+            ident_start: vec!['_', '$'],
+            ident_continue: vec!['_'],
+            operators: ["?=", "+=", "-=", "<-", "->"]
+                .iter()
+                .map(|op| op.to_string())
+                .collect(),
+            decorators: Vec::new(),
+        }
+    }
+}
+
+// Words that cannot be used as unquoted names
+pub(crate) const KEYWORDS: [&str; 8] = [
+    "true", "false", "null", "inf", "nan", "fn", "subsc", "indep",
+];
+
+impl Syntax {
+    pub fn is_ident_start(&self, c: char) -> bool {
+        if self.unicode_idents {
+            unicode_ident::is_xid_start(c) || self.ident_start.contains(&c)
+        } else {
+            c.is_ascii_alphabetic() || self.ident_start.contains(&c)
+        }
+    }
+
+    pub fn is_ident_continue(&self, c: char) -> bool {
+        if self.unicode_idents {
+            unicode_ident::is_xid_continue(c) || self.ident_continue.contains(&c)
+        } else {
+            c.is_ascii_alphanumeric() || self.ident_continue.contains(&c)
+        }
+    }
+
+    // Writes a dotted name, quoting the sections that cannot be written as they are
+    pub fn quote_path(&self, path: &[String]) -> String {
+        let mut result = String::new();
+        for (i, name) in path.iter().enumerate() {
+            if i > 0 {
+                result.push('.');
+            }
+            let mut chars = name.chars();
+            let plain = match chars.next() {
+                // Later sections may start with a digit, like an index
+                Some(c) if i == 0 => self.is_ident_start(c),
+                Some(c) => self.is_ident_continue(c),
+                None => false,
+            } && chars.all(|c| self.is_ident_continue(c))
+                && !(path.len() == 1
+                    && (KEYWORDS.contains(&name.as_str()) || self.decorators.contains(name)));
+            if plain {
+                result.push_str(name);
+            } else {
+                result.push('`');
+                for c in name.chars() {
+                    match c {
+                        '`' | '\\' => {
+                            result.push('\\');
+                            result.push(c);
+                        }
+                        _ => result.extend(c.escape_debug()),
+                    }
+                }
+                result.push('`');
+            }
+        }
+        result
+    }
+}
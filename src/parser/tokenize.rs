@@ -1,214 +1,926 @@
 use super::*;
 use crate::prelude::*;
+use std::borrow::Cow;
+use std::ops::Range;
 
-fn parse_slice(result: &mut BlockVec, slice: &[Token]) -> usize {
-    if slice.is_empty() {
-        return 0;
+// What parse_slice needs of a token type to build blocks out of a flat list of tokens
+pub(crate) trait Nested: Clone {
+    type Block;
+
+    fn new_block(decor: BlockDecorator) -> Self::Block;
+    fn push(block: &mut Self::Block, token: Self, span: Option<Span>);
+    fn from_block(block: Self::Block) -> Self;
+    fn operator(&self) -> Option<&str>;
+    fn decorator(&self) -> Option<&BlockDecorator>;
+}
+
+impl Nested for Token {
+    type Block = BlockVec;
+
+    fn new_block(decor: BlockDecorator) -> BlockVec {
+        let mut block = BlockVec::new();
+        block.set_decor(decor);
+        block
+    }
+
+    fn push(block: &mut BlockVec, token: Token, span: Option<Span>) {
+        match span {
+            Some(span) => block.push_spanned(token, span),
+            None => block.push(token),
+        }
+    }
+
+    fn from_block(block: BlockVec) -> Token {
+        Token::Block(block)
+    }
+
+    fn operator(&self) -> Option<&str> {
+        match self {
+            Token::Operator(op) => Some(op),
+            _ => None,
+        }
     }
+
+    fn decorator(&self) -> Option<&BlockDecorator> {
+        match self {
+            Token::Decorator(decor) => Some(decor),
+            _ => None,
+        }
+    }
+}
+
+// The closing bracket of a block opened with the given one
+fn closing(open: &str) -> Option<&'static str> {
+    match open {
+        "(" => Some(")"),
+        "{" => Some("}"),
+        _ => None,
+    }
+}
+
+// Returns the number of tokens consumed, including the closing bracket, and its span
+// The open span and bracket are the ones opening the block, or None at top level
+// Errors are collected and skipped over, so a best-effort tree is always built
+pub(crate) fn parse_slice<T: Nested>(
+    result: &mut T::Block,
+    slice: &[T],
+    spans: &[Option<Span>],
+    open: Option<(Span, &str)>,
+    errors: &mut Vec<SyntaxError>,
+) -> (usize, Option<Span>) {
     let mut i = 0;
     let mut decor = BlockDecorator::None;
     while i < slice.len() {
-        match slice[i] {
-            Token::Decorator(decor_) => {
-                decor = decor_;
-                i += 1;
+        if let Some(decor_) = slice[i].decorator() {
+            if slice.get(i + 1).and_then(|token| token.operator()) == Some("(") {
+                decor = decor_.clone();
+            } else {
+                errors.push(SyntaxError::at(
+                    spans[i].unwrap_or_default(),
+                    format!("Expected block after decorator {}", decor_.keyword()),
+                ));
             }
-            Token::Operator('(') => {
-                let mut block = BlockVec::new();
-
-                block.set_decor(decor);
-                decor = BlockDecorator::None;
-
-                i += parse_slice(&mut block, &slice[i + 1..]);
-                result.push(Token::Block(block));
+            i += 1;
+            continue;
+        }
+        match slice[i].operator() {
+            Some(bracket @ ("(" | "{")) => {
+                let decor = if bracket == "{" {
+                    BlockDecorator::Map
+                } else {
+                    std::mem::replace(&mut decor, BlockDecorator::None)
+                };
+                let mut block = T::new_block(decor);
+                let (count, close) = parse_slice(
+                    &mut block,
+                    &slice[i + 1..],
+                    &spans[i + 1..],
+                    Some((spans[i].unwrap_or_default(), bracket)),
+                    errors,
+                );
+                let span = match (spans[i], close) {
+                    (Some(open), Some(close)) => Some(open.to(&close)),
+                    (open, _) => open,
+                };
+                T::push(result, T::from_block(block), span);
+                i += count + 1;
             }
-            Token::Operator(')') => {
-                return i + 2;
+            Some(bracket @ (")" | "}")) => {
+                if open.and_then(|(_, open)| closing(open)) == Some(bracket) {
+                    return (i + 1, spans[i]);
+                }
+                errors.push(SyntaxError::at(
+                    spans[i].unwrap_or_default(),
+                    if bracket == ")" {
+                        "Unexpected closing parenthesis".to_string()
+                    } else {
+                        "Unexpected closing brace".to_string()
+                    },
+                ));
+                i += 1;
             }
             _ => {
-                result.push(slice[i].clone());
+                T::push(result, slice[i].clone(), spans[i]);
                 i += 1;
             }
         }
     }
-    slice.len()
+    if let Some((span, open)) = open {
+        errors.push(SyntaxError::at(
+            span,
+            if open == "(" {
+                "Unclosed block, expected closing parenthesis".to_string()
+            } else {
+                "Unclosed map, expected closing brace".to_string()
+            },
+        ));
+    }
+    (slice.len(), None)
 }
 
 pub trait Tokenize {
-    fn tokenize(&mut self) -> Option<Result<Token, SyntaxError>>;
+    fn tokenize(&mut self) -> Option<Result<(Token, Span), SyntaxError>>;
+
+    // Skips input after a failed tokenize, so that tokenizing can continue at a sensible point
+    // By default nothing is skipped, which suits tokenizers that consume the bad input
+    fn recover(&mut self) {}
 
     fn parse(&mut self) -> Result<Token, SyntaxError> {
         let mut stack = BlockVec::new();
         while let Some(token) = self.tokenize() {
-            stack.push(token?);
+            let (token, span) = token?;
+            stack.push_spanned(token, span);
+        }
+        let mut block = BlockVec::new();
+        let mut errors = Vec::new();
+        parse_slice(&mut block, stack.data(), stack.spans(), None, &mut errors);
+        match errors.into_iter().next() {
+            Some(e) => Err(e),
+            None => Ok(Token::Block(block)),
+        }
+    }
+
+    // Reads just enough tokens for the next top-level triple, returning it as a block
+    // This lets a scope be fed while the input is still being read
+    fn next_triple(&mut self) -> Option<Result<Token, SyntaxError>> {
+        let mut stack = BlockVec::new();
+        let mut depth = 0;
+        let mut count = 0;
+        while count < 3 {
+            let (token, span) = match self.tokenize() {
+                Some(token) => match token {
+                    Ok(token) => token,
+                    Err(e) => return Some(Err(e)),
+                },
+                None => break,
+            };
+            match token {
+                Token::Operator(ref op) if op == "(" || op == "{" => depth += 1,
+                Token::Operator(ref op) if (op == ")" || op == "}") && depth > 0 => {
+                    depth -= 1;
+                    if depth == 0 {
+                        count += 1;
+                    }
+                }
+                Token::Decorator(_) => {}
+                _ if depth == 0 => count += 1,
+                _ => {}
+            }
+            stack.push_spanned(token, span);
+        }
+        if stack.is_empty() {
+            return None;
+        }
+        let mut block = BlockVec::new();
+        let mut errors = Vec::new();
+        parse_slice(&mut block, stack.data(), stack.spans(), None, &mut errors);
+        match errors.into_iter().next() {
+            Some(e) => Some(Err(e)),
+            None => Some(Ok(Token::Block(block))),
+        }
+    }
+
+    // Parses the whole input, collecting every syntax error instead of stopping at the first
+    // The returned tree leaves out what could not be parsed, and errors are sorted by position
+    fn parse_recover(&mut self) -> (Token, Vec<SyntaxError>) {
+        let mut stack = BlockVec::new();
+        let mut errors = Vec::new();
+        // Where the current statement of each open block starts, and how many of its items
+        // have been read
+        let mut statements: Vec<(usize, usize)> = vec![(0, 0)];
+        while let Some(token) = self.tokenize() {
+            match token {
+                Ok((token, span)) => {
+                    let opens = matches!(&token, Token::Operator(op) if op == "(" || op == "{");
+                    let closes = matches!(&token, Token::Operator(op) if op == ")" || op == "}")
+                        && statements.len() > 1;
+                    let decorator = matches!(token, Token::Decorator(_));
+                    stack.push_spanned(token, span);
+                    if opens {
+                        statements.push((stack.len(), 0));
+                    } else if !decorator {
+                        if closes {
+                            statements.pop();
+                        }
+                        let (start, count) = statements.last_mut().unwrap();
+                        *count += 1;
+                        if (*count).is_multiple_of(3) {
+                            *start = stack.len();
+                        }
+                    }
+                }
+                Err(e) => {
+                    // An error at the same place again means recovering skipped nothing
+                    let stuck = errors.last().is_some_and(|last: &SyntaxError| {
+                        (last.line(), last.cursor()) == (e.line(), e.cursor())
+                    });
+                    if stuck {
+                        break;
+                    }
+                    errors.push(e);
+                    // What was read of the broken statement is dropped along with it, so the
+                    // statements after it keep their place
+                    let (start, count) = statements.last_mut().unwrap();
+                    while stack.len() > *start {
+                        stack.pop();
+                    }
+                    *count = 0;
+                    self.recover();
+                }
+            }
         }
         let mut block = BlockVec::new();
-        parse_slice(&mut block, stack.data());
-        Ok(Token::Block(block))
+        parse_slice(&mut block, stack.data(), stack.spans(), None, &mut errors);
+        errors.sort_by_key(|e| (e.line(), e.cursor()));
+        (Token::Block(block), errors)
     }
 }
 
-impl Parser {
-    fn escape_char(c: char) -> Result<char, SyntaxError> {
-        match c {
-            'n' => Ok('\n'),
-            'r' => Ok('\r'),
-            't' => Ok('\t'),
-            '\\' => Ok('\\'),
-            '"' => Ok('"'),
-            _ => Err(SyntaxError::new(
-                0,
-                0,
-                format!("Unknown escape character {}", c),
-            )),
-        }
-    }
-    fn next_number(&mut self) -> Result<Token, SyntaxError> {
-        let mut num = String::new();
-        let mut dot = false;
-        num.push(self.next().unwrap());
-        while let Some(c) = self.peek() {
-            if c.is_digit(10) {
-                num.push(c);
-                self.next();
-            } else if c == '.' {
-                if dot {
-                    return Err(self.error(format!("Unexpected float number {}{}", num, c)));
-                } else {
-                    dot = true;
-                    num.push(c);
-                    self.next();
+// Decodes an escape sequence, given the chars right after the backslash
+fn decode_escape(chars: &mut std::str::Chars) -> Result<char, String> {
+    match chars.next() {
+        Some('n') => Ok('\n'),
+        Some('r') => Ok('\r'),
+        Some('t') => Ok('\t'),
+        Some('0') => Ok('\0'),
+        Some('\\') => Ok('\\'),
+        Some('"') => Ok('"'),
+        Some('\'') => Ok('\''),
+        Some('`') => Ok('`'),
+        Some('u') => {
+            if chars.next() != Some('{') {
+                return Err("Expected '{' in unicode escape".to_string());
+            }
+            let mut hex = String::new();
+            loop {
+                match chars.next() {
+                    Some('}') => break,
+                    Some(c) if c.is_ascii_hexdigit() && hex.len() < 6 => hex.push(c),
+                    _ => {
+                        return Err(
+                            "Invalid unicode escape, expected \\u{...} with 1 to 6 hex digits"
+                                .to_string(),
+                        )
+                    }
                 }
-            } else {
-                break;
             }
+            u32::from_str_radix(&hex, 16)
+                .ok()
+                .and_then(char::from_u32)
+                .ok_or_else(|| format!("Unicode escape \\u{{{}}} is not a valid char", hex))
         }
-        if dot {
-            Ok(Token::Value(Value::Float(num.parse().unwrap())))
+        Some(c) => Err(format!("Unknown escape character {}", c.escape_debug())),
+        None => Err("Expected escape character".to_string()),
+    }
+}
+
+// Decodes escapes that were already validated while tokenizing
+pub(crate) fn unescape(raw: &str) -> String {
+    let mut string = String::new();
+    let mut chars = raw.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            string.push(decode_escape(&mut chars).unwrap());
         } else {
-            Ok(Token::Value(Value::Int(num.parse().unwrap())))
-        }
-    }
-
-    fn next_string(&mut self) -> Result<Token, SyntaxError> {
-        let mut string = String::new();
-        let mut escape = false;
-        self.next();
-        while let Some(c) = self.peek() {
-            if escape {
-                string.push(Self::escape_char(c)?);
-                escape = false;
-            } else if c == '\\' {
-                escape = true;
-            } else if c == '"' {
-                self.next();
+            string.push(c);
+        }
+    }
+    string
+}
+
+// A token as read from the input, its text being a byte range of the source, so that it can be
+// either copied into a Token or borrowed by a TokenRef
+pub(crate) enum Lexeme {
+    Value(Value),
+    // A string without escapes
+    Str(Range<usize>),
+    // A string whose escapes are yet to be decoded
+    Escaped(Range<usize>),
+    // A text block, which is always decoded
+    Text(String),
+    Symbol(Range<usize>),
+    // A tag without its brackets
+    Tag(Range<usize>),
+    Operator(Range<usize>),
+    Decorator(BlockDecorator),
+}
+
+impl Lexeme {
+    pub(crate) fn into_token(self, source: &str) -> Token {
+        match self.into_ref(source) {
+            TokenRef::Value(value) => Token::Value(value),
+            TokenRef::Str(str) => Token::Value(Value::Str(str.into_owned())),
+            TokenRef::Symbol(path) => Token::Symbol(path.to_vec()),
+            TokenRef::Tag(path) => Token::Tag(path.to_vec()),
+            TokenRef::Operator(op) => Token::Operator(op.to_string()),
+            TokenRef::Decorator(decor) => Token::Decorator(decor),
+            TokenRef::Block(_) => unreachable!(),
+        }
+    }
+
+    pub(crate) fn into_ref(self, source: &str) -> TokenRef<'_> {
+        match self {
+            Lexeme::Value(value) => TokenRef::Value(value),
+            Lexeme::Str(range) => TokenRef::Str(Cow::Borrowed(&source[range])),
+            Lexeme::Escaped(range) => TokenRef::Str(Cow::Owned(unescape(&source[range]))),
+            Lexeme::Text(text) => TokenRef::Str(Cow::Owned(text)),
+            Lexeme::Symbol(range) => TokenRef::Symbol(PathRef::new(&source[range])),
+            Lexeme::Tag(range) => TokenRef::Tag(PathRef::new(&source[range])),
+            Lexeme::Operator(range) => TokenRef::Operator(&source[range]),
+            Lexeme::Decorator(decor) => TokenRef::Decorator(decor),
+        }
+    }
+}
+
+impl Parser<'_> {
+    // Consumes chars while pred holds, appending them to buf
+    fn take_while(&mut self, buf: &mut String, pred: impl Fn(char) -> bool) {
+        while let Some(c) = self.peek_char() {
+            if !pred(c) {
                 break;
-            } else {
-                string.push(c);
             }
-            self.next();
+            buf.push(c);
+            self.next_char();
+        }
+    }
+
+    // Removes digit separators, which are only allowed between two digits
+    fn strip_separators(mark: Span, literal: &str, digits: &str) -> Result<String, SyntaxError> {
+        let chars: Vec<char> = digits.chars().collect();
+        for (i, c) in chars.iter().enumerate() {
+            if *c == '_'
+                && !(i > 0
+                    && chars[i - 1].is_ascii_alphanumeric()
+                    && i + 1 < chars.len()
+                    && chars[i + 1].is_ascii_alphanumeric())
+            {
+                return Err(SyntaxError::at(
+                    mark,
+                    format!("Misplaced digit separator in number {}", literal),
+                ));
+            }
         }
-        Ok(Token::Value(Value::Str(string)))
+        Ok(digits.replace('_', ""))
     }
 
-    fn next_tag(&mut self) -> Result<Token, SyntaxError> {
-        let mut tag = vec![String::new()];
-        self.next();
-        while let Some(c) = self.peek() {
-            if c == ']' {
-                self.next();
+    fn next_number(&mut self) -> Result<Value, SyntaxError> {
+        let mark = self.mark();
+        let mut num = String::new();
+        if self.peek_char() == Some('-') {
+            num.push('-');
+            self.next_char();
+            if self.at_word("inf") {
+                self.take_while(&mut num, |c| c.is_ascii_alphabetic());
+                return Ok(Value::Float(f64::NEG_INFINITY));
+            }
+        }
+
+        let mut literal = num.clone();
+        let radix = match (self.peek_char(), self.peek_nth(1)) {
+            (Some('0'), Some('x')) => 16,
+            (Some('0'), Some('o')) => 8,
+            (Some('0'), Some('b')) => 2,
+            _ => 10,
+        };
+        if radix != 10 {
+            literal.push(self.next_char().unwrap());
+            literal.push(self.next_char().unwrap());
+            let mut digits = String::new();
+            self.take_while(&mut digits, |c| c.is_ascii_alphanumeric() || c == '_');
+            literal.push_str(&digits);
+            let digits = Self::strip_separators(mark, &literal, &digits)?;
+            if digits.is_empty() {
+                return Err(SyntaxError::at(
+                    mark,
+                    format!("Expected digits in number {}", literal),
+                ));
+            }
+            if let Some(c) = digits.chars().find(|c| !c.is_digit(radix)) {
+                return Err(SyntaxError::at(
+                    mark,
+                    format!("Invalid digit '{}' in number {}", c, literal),
+                ));
+            }
+            num.push_str(&digits);
+            return match i64::from_str_radix(&num, radix) {
+                Ok(i) => Ok(Value::Int(i)),
+                Err(_) => Err(SyntaxError::at(
+                    mark,
+                    format!("Integer {} is out of range", literal),
+                )),
+            };
+        }
+
+        let mut float = false;
+        let mut digits = String::new();
+        self.take_while(&mut digits, |c| c.is_ascii_digit() || c == '_');
+        if self.peek_char() == Some('.') {
+            float = true;
+            digits.push('.');
+            self.next_char();
+            if !matches!(self.peek_char(), Some('0'..='9')) {
+                literal.push_str(&digits);
+                return Err(SyntaxError::at(
+                    mark,
+                    format!("Expected digits after decimal point in number {}", literal),
+                ));
+            }
+            self.take_while(&mut digits, |c| c.is_ascii_digit() || c == '_');
+        }
+        if let Some('e' | 'E') = self.peek_char() {
+            float = true;
+            digits.push(self.next_char().unwrap());
+            if let Some(c @ ('+' | '-')) = self.peek_char() {
+                digits.push(c);
+                self.next_char();
+            }
+            if !matches!(self.peek_char(), Some('0'..='9')) {
+                literal.push_str(&digits);
+                return Err(SyntaxError::at(
+                    mark,
+                    format!("Expected digits in exponent of number {}", literal),
+                ));
+            }
+            self.take_while(&mut digits, |c| c.is_ascii_digit() || c == '_');
+        }
+        literal.push_str(&digits);
+        let mut unit = String::new();
+        if self.peek_char().is_some_and(|c| c.is_ascii_alphabetic()) {
+            self.take_while(&mut unit, |c| c.is_ascii_alphabetic());
+            literal.push_str(&unit);
+        }
+        if let Some(c) = self.peek_char() {
+            if c.is_alphanumeric() || c == '_' || c == '.' {
+                return Err(SyntaxError::at(
+                    mark,
+                    format!("Unexpected character '{}' after number {}", c, literal),
+                ));
+            }
+        }
+
+        // Separators must sit between digits, so check each part on its own
+        for part in digits.split(['.', 'e', 'E', '+', '-']) {
+            Self::strip_separators(mark, &literal, part)?;
+        }
+        num.push_str(&digits.replace('_', ""));
+        if !unit.is_empty() {
+            return Self::with_unit(mark, &literal, &num, float, &unit);
+        }
+        if float {
+            match num.parse::<f64>() {
+                Ok(f) if f.is_finite() => Ok(Value::Float(f)),
+                _ => Err(SyntaxError::at(
+                    mark,
+                    format!("Float {} is out of range", literal),
+                )),
+            }
+        } else {
+            match num.parse::<i64>() {
+                Ok(i) => Ok(Value::Int(i)),
+                Err(_) => Err(SyntaxError::at(
+                    mark,
+                    format!("Integer {} is out of range", literal),
+                )),
+            }
+        }
+    }
+
+    // Turns a number followed by a unit, like 30s or 1.5GB, into a duration or a size
+    // Whole amounts may go beyond the range of Int, as long as the result fits
+    fn with_unit(
+        mark: Span,
+        literal: &str,
+        num: &str,
+        float: bool,
+        unit: &str,
+    ) -> Result<Value, SyntaxError> {
+        let error = |msg: &str| SyntaxError::at(mark, format!("{} {}", msg, literal));
+        let (scale, duration) = match (duration_unit(unit), size_unit(unit)) {
+            (Some(nanos), _) => (nanos, true),
+            (_, Some(bytes)) => (bytes as u128, false),
+            _ => return Err(error(&format!("Unknown unit {} in number", unit))),
+        };
+        if num.starts_with('-') {
+            return Err(error("Expected a positive amount in"));
+        }
+        let amount = if float {
+            let amount = num.parse::<f64>().unwrap_or(f64::INFINITY) * scale as f64;
+            // Allow for the rounding of the float, but not for a fraction of the unit
+            if (amount - amount.round()).abs() > 1e-3 {
+                let what = if duration { "nanoseconds" } else { "bytes" };
+                return Err(error(&format!("Expected a whole number of {} in", what)));
+            }
+            (amount < u128::MAX as f64).then_some(amount.round() as u128)
+        } else {
+            num.parse::<u128>().ok().and_then(|n| n.checked_mul(scale))
+        };
+        let value = if duration {
+            amount.and_then(duration_from_nanos).map(Value::Duration)
+        } else {
+            amount.and_then(|n| u64::try_from(n).ok()).map(Value::Size)
+        };
+        value.ok_or_else(|| error("Amount is out of range in"))
+    }
+
+    // Consumes an escape sequence starting with the backslash, reporting errors at the backslash
+    fn next_escape(&mut self) -> Result<char, SyntaxError> {
+        let mark = self.mark();
+        let rest = self.rest();
+        let mut chars = rest.chars();
+        chars.next();
+        let result = decode_escape(&mut chars);
+        let count = rest[..rest.len() - chars.as_str().len()].chars().count();
+        for _ in 0..count {
+            self.next_char();
+        }
+        result.map_err(|msg| SyntaxError::at(mark, msg))
+    }
+
+    fn next_string(&mut self) -> Result<Lexeme, SyntaxError> {
+        if self.rest().starts_with("\"\"\"") {
+            return self.next_text_block();
+        }
+        let mark = self.mark();
+        self.next_char();
+        let begin = self.pos();
+        let mut escaped = false;
+        loop {
+            match self.peek_char() {
+                None => {
+                    return Err(SyntaxError::at(mark, "Unterminated string".to_string()));
+                }
+                Some('\\') => {
+                    self.next_escape()?;
+                    escaped = true;
+                }
+                Some('"') => {
+                    let range = begin..self.pos();
+                    self.next_char();
+                    return Ok(if escaped {
+                        Lexeme::Escaped(range)
+                    } else {
+                        Lexeme::Str(range)
+                    });
+                }
+                Some(_) => {
+                    self.next_char();
+                }
+            }
+        }
+    }
+
+    // A bytes literal is written as b"..." holding text and \xNN escapes, or as hex"..." or
+    // b64"..." holding encoded bytes
+    fn next_bytes(&mut self) -> Result<Lexeme, SyntaxError> {
+        let mark = self.mark();
+        let prefix = self.at_bytes().unwrap();
+        for _ in 0..=prefix.len() {
+            self.next_char();
+        }
+        let mut bytes = Vec::new();
+        let begin = self.pos();
+        loop {
+            match self.peek_char() {
+                None => {
+                    return Err(SyntaxError::at(mark, "Unterminated bytes".to_string()));
+                }
+                Some('"') => break,
+                Some('\\') if prefix == "b" && self.peek_nth(1) == Some('x') => {
+                    let escape = self.mark();
+                    let digits = self.rest().get(2..4).unwrap_or("");
+                    match u8::from_str_radix(digits, 16) {
+                        Ok(byte) if digits.chars().all(|c| c.is_ascii_hexdigit()) => {
+                            bytes.push(byte)
+                        }
+                        _ => {
+                            return Err(SyntaxError::at(
+                                escape,
+                                "Invalid byte escape, expected \\x with 2 hex digits".to_string(),
+                            ))
+                        }
+                    }
+                    for _ in 0..4 {
+                        self.next_char();
+                    }
+                }
+                Some('\\') if prefix == "b" => {
+                    let c = self.next_escape()?;
+                    bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                }
+                Some(c) => {
+                    if prefix == "b" {
+                        bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                    }
+                    self.next_char();
+                }
+            }
+        }
+        let text = &self.source()[begin..self.pos()];
+        let bytes = match prefix {
+            "hex" => decode_hex(text),
+            "b64" => decode_base64(text),
+            _ => Ok(bytes),
+        }
+        .map_err(|msg| SyntaxError::at(mark, msg))?;
+        self.next_char();
+        Ok(Lexeme::Value(Value::Bytes(bytes)))
+    }
+
+    // A raw string is written as r"..." or r#"..."#, with any number of matching '#'
+    fn next_raw_string(&mut self) -> Result<Lexeme, SyntaxError> {
+        let mark = self.mark();
+        self.next_char();
+        let mut hashes = 0;
+        while self.peek_char() == Some('#') {
+            hashes += 1;
+            self.next_char();
+        }
+        self.next_char();
+        let close = format!("\"{}", "#".repeat(hashes));
+        let begin = self.pos();
+        loop {
+            if self.rest().starts_with(&close) {
+                let range = begin..self.pos();
+                for _ in 0..close.len() {
+                    self.next_char();
+                }
+                return Ok(Lexeme::Str(range));
+            }
+            if self.next_char().is_none() {
+                return Err(SyntaxError::at(mark, "Unterminated raw string".to_string()));
+            }
+        }
+    }
+
+    // A text block is wrapped in triple quotes and may span multiple lines
+    // A line break right after the opening quotes is dropped, so is a last line holding only
+    // whitespace, and the indentation shared by all non-blank lines is stripped
+    fn next_text_block(&mut self) -> Result<Lexeme, SyntaxError> {
+        let mark = self.mark();
+        for _ in 0..3 {
+            self.next_char();
+        }
+        // Escapes are validated here where positions are known, and decoded after stripping
+        let mut raw = String::new();
+        loop {
+            if self.rest().starts_with("\"\"\"") {
+                for _ in 0..3 {
+                    self.next_char();
+                }
                 break;
-            } else if c == '.' {
-                if tag.last().unwrap().is_empty() {
-                    return Err(self.error(format!("Empty tag section")));
+            }
+            match self.peek_char() {
+                None => {
+                    return Err(SyntaxError::at(mark, "Unterminated text block".to_string()));
+                }
+                Some('\\') => {
+                    let begin = self.pos();
+                    self.next_escape()?;
+                    raw.push_str(&self.source()[begin..self.pos()]);
+                }
+                Some(c) => {
+                    raw.push(c);
+                    self.next_char();
                 }
-                tag.push(String::new());
-            } else {
-                tag.last_mut().unwrap().push(c);
             }
-            self.next();
         }
-        if tag.last().unwrap().is_empty() {
-            return Err(self.error(format!("Empty tag section")));
+
+        let raw = raw.strip_prefix('\n').unwrap_or(&raw);
+        let mut lines: Vec<&str> = raw.split('\n').collect();
+        if lines.len() > 1 && lines.last().unwrap().trim().is_empty() {
+            lines.pop();
+            lines.push("");
+        }
+        let indent = lines
+            .iter()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| line.len() - line.trim_start().len())
+            .min()
+            .unwrap_or(0);
+        let text = lines
+            .iter()
+            .map(|line| line.get(indent..).unwrap_or(line.trim_start()))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        // Escapes were validated while reading
+        Ok(Lexeme::Text(unescape(&text)))
+    }
+
+    // Skips a name section quoted in backticks, which may hold any char but line breaks
+    fn next_quoted(&mut self) -> Result<(), SyntaxError> {
+        let mark = self.mark();
+        self.next_char();
+        let begin = self.pos();
+        loop {
+            match self.peek_char() {
+                None | Some('\n') => {
+                    return Err(SyntaxError::at(
+                        mark,
+                        "Unterminated quoted name".to_string(),
+                    ));
+                }
+                Some('\\') => {
+                    self.next_escape()?;
+                }
+                Some('`') => {
+                    if self.pos() == begin {
+                        return Err(SyntaxError::at(mark, "Empty quoted name".to_string()));
+                    }
+                    self.next_char();
+                    return Ok(());
+                }
+                Some(_) => {
+                    self.next_char();
+                }
+            }
         }
-        Ok(Token::Tag(tag))
     }
 
-    fn next_ident(&mut self) -> Result<Token, SyntaxError> {
-        let mut symbol = vec![String::new()];
-        symbol.last_mut().unwrap().push(self.next().unwrap());
-        while let Some(c) = self.peek() {
-            match c {
-                'a'..='z' | 'A'..='Z' | '_' | '0'..='9' => {
-                    symbol.last_mut().unwrap().push(c);
-                    self.next();
+    fn next_tag(&mut self) -> Result<Lexeme, SyntaxError> {
+        let mark = self.mark();
+        self.next_char();
+        let begin = self.pos();
+        let mut empty = true;
+        let range = loop {
+            match self.peek_char() {
+                None => return Err(SyntaxError::at(mark, "Unterminated tag".to_string())),
+                Some(']') => {
+                    let range = begin..self.pos();
+                    self.next_char();
+                    break range;
+                }
+                Some('.') => {
+                    if empty {
+                        return Err(self.error("Empty tag section".to_string()));
+                    }
+                    empty = true;
+                    self.next_char();
+                }
+                Some('`') if empty => {
+                    self.next_quoted()?;
+                    empty = false;
+                    if !matches!(self.peek_char(), Some('.' | ']')) {
+                        return Err(self.error("Expected '.' or ']' after quoted name".to_string()));
+                    }
                 }
-                '.' => {
-                    if symbol.last().unwrap().is_empty() {
-                        return Err(self.error(format!("Empty symbol section")));
+                Some(_) => {
+                    empty = false;
+                    self.next_char();
+                }
+            }
+        };
+        if empty {
+            return Err(self.error("Empty tag section".to_string()));
+        }
+        Ok(Lexeme::Tag(range))
+    }
+
+    fn next_ident(&mut self) -> Result<Lexeme, SyntaxError> {
+        let begin = self.pos();
+        let mut sections = 0;
+        let mut quoted = false;
+        loop {
+            // Each section is either an identifier or any name quoted in backticks
+            if self.peek_char() == Some('`') {
+                self.next_quoted()?;
+                quoted = true;
+            } else {
+                let start = self.pos();
+                while let Some(c) = self.peek_char() {
+                    if !(self.syntax().is_ident_continue(c)
+                        || self.pos() == start && self.syntax().is_ident_start(c))
+                    {
+                        break;
                     }
-                    symbol.push(String::new());
-                    self.next();
+                    self.next_char();
+                }
+                if self.pos() == start {
+                    return Err(self.error("Empty symbol section".to_string()));
                 }
-                _ => break,
             }
+            sections += 1;
+            if self.peek_char() != Some('.') {
+                break;
+            }
+            self.next_char();
         }
-        if symbol.last().unwrap().is_empty() {
-            return Err(self.error(format!("Empty symbol section")));
+        let range = begin..self.pos();
+        if sections > 1 || quoted {
+            return Ok(Lexeme::Symbol(range));
         }
-        if symbol.len() == 1 {
-            match symbol.last().unwrap().as_str() {
-                "true" => Ok(Token::Value(Value::Bool(true))),
-                "false" => Ok(Token::Value(Value::Bool(false))),
-                "null" => Ok(Token::Value(Value::Null)),
-                "fn" => Ok(Token::Decorator(BlockDecorator::Functor)),
-                "subsc" => Ok(Token::Decorator(BlockDecorator::SubScope)),
-                "indep" => Ok(Token::Decorator(BlockDecorator::IndepScope)),
-                _ => Ok(Token::Symbol(symbol)),
+        Ok(match &self.source()[range.clone()] {
+            "true" => Lexeme::Value(Value::Bool(true)),
+            "inf" => Lexeme::Value(Value::Float(f64::INFINITY)),
+            "nan" => Lexeme::Value(Value::Float(f64::NAN)),
+            "false" => Lexeme::Value(Value::Bool(false)),
+            "null" => Lexeme::Value(Value::Null),
+            "fn" => Lexeme::Decorator(BlockDecorator::Functor),
+            "subsc" => Lexeme::Decorator(BlockDecorator::SubScope),
+            "indep" => Lexeme::Decorator(BlockDecorator::IndepScope),
+            name if self.syntax().decorators.iter().any(|d| d == name) => {
+                Lexeme::Decorator(BlockDecorator::Custom(name.to_string()))
             }
-        } else {
-            Ok(Token::Symbol(symbol))
+            _ => Lexeme::Symbol(range),
+        })
+    }
+
+    fn next_operator(&mut self) -> Lexeme {
+        let begin = self.pos();
+        // The longest operator of the syntax wins, otherwise it is a single char
+        let len = self
+            .syntax()
+            .operators
+            .iter()
+            .filter(|op| self.rest().starts_with(op.as_str()))
+            .map(|op| op.chars().count())
+            .max()
+            .unwrap_or(1);
+        for _ in 0..len {
+            self.next_char();
         }
+        Lexeme::Operator(begin..self.pos())
     }
 
-    fn next_operator(&mut self) -> Result<Token, SyntaxError> {
-        Ok(Token::Operator(self.next().unwrap()))
+    fn skip_comment(&mut self) {
+        while let Some(c) = self.next_char() {
+            if c == '\n' {
+                break;
+            }
+        }
     }
 
-    fn next_after_comment(&mut self) -> Option<Result<Token, SyntaxError>> {
+    // Reads the next token, leaving its text in the source to be either copied or borrowed
+    pub(crate) fn next_lexeme(&mut self) -> Option<Result<(Lexeme, Span), SyntaxError>> {
         loop {
-            match self.peek() {
-                None => return None,
-                Some('\n') => {
-                    self.next();
-                    break;
+            let c = self.peek_char()?;
+            if c.is_whitespace() {
+                self.next_char();
+                continue;
+            }
+            let mark = self.mark();
+            self.set_last_mark(mark);
+            let lexeme = match c {
+                '0'..='9' => self.next_number().map(Lexeme::Value),
+                '-' if matches!(self.peek_nth(1), Some('0'..='9')) || self.at_word("-inf") => {
+                    self.next_number().map(Lexeme::Value)
                 }
-                _ => {
-                    self.next();
+                '"' => self.next_string(),
+                'r' if self.at_raw_string() => self.next_raw_string(),
+                'b' | 'h' if self.at_bytes().is_some() => self.next_bytes(),
+                '[' => self.next_tag(),
+                '`' => self.next_ident(),
+                c if self.syntax().is_ident_start(c) => self.next_ident(),
+                '#' => {
+                    self.skip_comment();
+                    continue;
                 }
-            }
+                _ => Ok(self.next_operator()),
+            };
+            return Some(lexeme.map(|lexeme| (lexeme, self.span_from(mark))));
         }
-        self.tokenize()
     }
 }
 
-impl Tokenize for Parser {
-    fn tokenize(&mut self) -> Option<Result<Token, SyntaxError>> {
-        if let Some(mut c) = self.peek() {
-            while c.is_whitespace() {
-                self.next();
-                c = self.peek()?;
-            }
-            match c {
-                '0'..='9' | '-' => Some(self.next_number()),
-                '"' => Some(self.next_string()),
-                '[' => Some(self.next_tag()),
-                'a'..='z' | 'A'..='Z' | '_' | '$' => Some(self.next_ident()),
-                '#' => self.next_after_comment(),
-                _ => Some(self.next_operator()),
+impl Tokenize for Parser<'_> {
+    // Restarts right after the first char of the failed token, then skips to the next line
+    // or closing parenthesis
+    fn recover(&mut self) {
+        self.reset(self.last_mark());
+        self.next_char();
+        while let Some(c) = self.peek_char() {
+            if c == ')' {
+                break;
+            }
+            self.next_char();
+            if c == '\n' {
+                break;
             }
-        } else {
-            None
         }
     }
+
+    fn tokenize(&mut self) -> Option<Result<(Token, Span), SyntaxError>> {
+        let result = self.next_lexeme()?;
+        Some(result.map(|(lexeme, span)| (lexeme.into_token(self.source()), span)))
+    }
 }
@@ -4,14 +4,18 @@ pub use std::error::Error;
 pub use std::fmt::{Debug, Display, Formatter, Result as FmtResult};
 pub use std::rc::{Rc, Weak};
 
+pub use crate::convert::*;
+pub use crate::cst::*;
 pub use crate::error::*;
 pub use crate::init::*;
 pub use crate::parser::*;
 pub use crate::scope::*;
+#[cfg(feature = "serde")]
+pub use crate::serial::*;
 pub use crate::value::*;
 
-pub static ANONYMOUS: &'static str = "[anonymous]";
-pub static RETURN: &'static str = "ret";
+pub static ANONYMOUS: &str = "[anonymous]";
+pub static RETURN: &str = "ret";
 
 pub(crate) type RcCell<T> = Rc<RefCell<T>>;
 
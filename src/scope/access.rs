@@ -1,31 +1,40 @@
 use crate::prelude::*;
 
+#[allow(clippy::wrong_self_convention)]
 impl Scope {
-    pub(crate) fn to_scope(&mut self, token: &Token) -> Result<ScopeRc, RuntimeError> {
+    pub fn to_scope(&mut self, token: &Token) -> Result<ScopeRc, RuntimeError> {
         match token {
             Token::Tag(name) => Ok(self.query_scope(name)?),
             _ => {
                 if let Token::Block(block) = token {
-                    match block.decor() {
-                        BlockDecorator::SubScope => return self.to_subscope(token),
-                        _ => {}
+                    if *block.decor() == BlockDecorator::SubScope {
+                        return self.to_subscope(token);
+                    }
+                    if let Some(hook) = self.decorator_hook(block.decor())? {
+                        if let Some(result) = hook.to_scope(self, block) {
+                            return result;
+                        }
                     }
                 }
-                let mut scope = Scope::new();
+                let mut scope = self.new_indep();
                 scope.feed(token)?;
                 Ok(rc_cell(scope))
             }
         }
     }
 
-    pub(crate) fn to_subscope(&mut self, token: &Token) -> Result<ScopeRc, RuntimeError> {
+    pub fn to_subscope(&mut self, token: &Token) -> Result<ScopeRc, RuntimeError> {
         match token {
             Token::Tag(name) => Ok(self.query_scope(name)?),
             _ => {
                 if let Token::Block(block) = token {
-                    match block.decor() {
-                        BlockDecorator::IndepScope => return self.to_scope(token),
-                        _ => {}
+                    if *block.decor() == BlockDecorator::IndepScope {
+                        return self.to_scope(token);
+                    }
+                    if let Some(hook) = self.decorator_hook(block.decor())? {
+                        if let Some(result) = hook.to_subscope(self, block) {
+                            return result;
+                        }
                     }
                 }
                 let scope = Scope::with_parent(ANONYMOUS.to_string(), self)?;
@@ -35,7 +44,7 @@ impl Scope {
         }
     }
 
-    pub(crate) fn to_list(&mut self, block: &BlockVec) -> Result<ValueRc, RuntimeError> {
+    pub fn to_list(&mut self, block: &BlockVec) -> Result<ValueRc, RuntimeError> {
         let mut list = VecDeque::new();
         for token in block.data() {
             list.push_back(self.as_true_value(token)?);
@@ -43,11 +52,64 @@ impl Scope {
         Ok(rc_cell(Value::List(list)))
     }
 
-    pub(crate) fn to_functor(&mut self, block: &BlockVec) -> Result<ValueRc, RuntimeError> {
+    // Evaluates a map literal, made of key = value triples, or key ~ value to link the value
+    pub fn to_map(&mut self, block: &BlockVec) -> Result<ValueRc, RuntimeError> {
+        if !block.len().is_multiple_of(3) {
+            return Err(RuntimeError::new(format!(
+                "[Map] Map literal length must be a multiple of 3, but got {}",
+                block.len()
+            ))
+            .with_span(block.span(block.len() - block.len() % 3)));
+        }
+        let mut map = Map::new();
+        for (i, triple) in block.data().chunks_exact(3).enumerate() {
+            let key = match &triple[0] {
+                Token::Symbol(path) if path.len() == 1 => path[0].clone(),
+                Token::Value(Value::Str(key)) => key.clone(),
+                key => {
+                    return Err(RuntimeError::new(format!(
+                        "[Map] Expected a name or string as key, but got {}",
+                        key
+                    ))
+                    .with_span(block.span(i * 3)))
+                }
+            };
+            let value = match &triple[1] {
+                Token::Operator(op) if op == "=" => self.as_true_value(&triple[2]),
+                Token::Operator(op) if op == "~" => self.as_linked_value(&triple[2]),
+                op => {
+                    return Err(RuntimeError::new(format!(
+                        "[Map] Expected = or ~ in map literal, but got {}",
+                        op
+                    ))
+                    .with_span(block.span(i * 3 + 1)))
+                }
+            }
+            .map_err(|e| e.with_span(block.span(i * 3 + 2)))?;
+            map.insert(key, value);
+        }
+        Ok(rc_cell(Value::Map(map)))
+    }
+
+    // Evaluates a decorated block or a tag into a scope value
+    pub fn to_scope_value(&mut self, token: &Token) -> Result<ValueRc, RuntimeError> {
+        let scope = match token {
+            Token::Block(block) if *block.decor() == BlockDecorator::SubScope => {
+                let scope = self.to_subscope(token)?;
+                // The scope may outlive this one, so it only sees outer names while evaluated
+                self.detach_anonymous(&scope);
+                scope
+            }
+            _ => self.to_scope(token)?,
+        };
+        Ok(rc_cell(Value::Scope(scope)))
+    }
+
+    pub fn to_functor(&mut self, block: &BlockVec) -> Result<ValueRc, RuntimeError> {
         let token = Token::Block(block.clone());
         Ok(rc_cell(Value::Functor(Functor::new(move |scope| {
             scope.feed(&token)?;
-            Ok(scope.query_value(&[RETURN.to_string()])?)
+            scope.query_value(&[RETURN.to_string()])
         }))))
     }
 
@@ -66,35 +128,55 @@ impl Scope {
         }
     }
 
-    pub(crate) fn as_true_value(&mut self, token: &Token) -> Result<ValueRc, RuntimeError> {
+    pub fn as_true_value(&mut self, token: &Token) -> Result<ValueRc, RuntimeError> {
         match token {
             Token::Value(value) => Ok(rc_cell(value.clone())),
             Token::Symbol(name) => Ok(rc_clone(self.query_value(name)?)),
+            Token::Tag(name) => Ok(rc_cell(Value::Scope(self.query_scope(name)?))),
             Token::Block(block) => {
+                if let Some(hook) = self.decorator_hook(block.decor())? {
+                    if let Some(result) = hook.as_value(self, block) {
+                        return result;
+                    }
+                }
                 match block.decor() {
                     BlockDecorator::Functor => self.to_functor(block),
+                    BlockDecorator::Map => self.to_map(block),
+                    BlockDecorator::SubScope | BlockDecorator::IndepScope => {
+                        self.to_scope_value(token)
+                    }
                     _ => self.to_list(block),
                 }
             },
             _ => Err(RuntimeError::new(format!(
-                "[Eval] Expected value-convertible type (including literal values, symbols, and blocks(aka lists)), but got {}",
+                "[Eval] Expected value-convertible type (including literal values, symbols, tags, and blocks(aka lists)), but got {}",
                 token
             ))),
         }
     }
 
-    pub(crate) fn as_linked_value(&mut self, token: &Token) -> Result<ValueRc, RuntimeError> {
+    pub fn as_linked_value(&mut self, token: &Token) -> Result<ValueRc, RuntimeError> {
         match token {
             Token::Value(value) => Ok(rc_cell(value.clone())),
             Token::Symbol(name) => Ok(self.query_value(name)?),
+            Token::Tag(name) => Ok(rc_cell(Value::Scope(self.query_scope(name)?))),
             Token::Block(block) => {
+                if let Some(hook) = self.decorator_hook(block.decor())? {
+                    if let Some(result) = hook.as_value(self, block) {
+                        return result;
+                    }
+                }
                 match block.decor() {
                     BlockDecorator::Functor => self.to_functor(block),
+                    BlockDecorator::Map => self.to_map(block),
+                    BlockDecorator::SubScope | BlockDecorator::IndepScope => {
+                        self.to_scope_value(token)
+                    }
                     _ => self.to_list(block),
                 }
             },
             _ => Err(RuntimeError::new(format!(
-                "[LinkEval] Expected value-convertible type (including literal values, symbols, and blocks(aka lists)), but got {}",
+                "[LinkEval] Expected value-convertible type (including literal values, symbols, tags, and blocks(aka lists)), but got {}",
                 token
             ))),
         }
@@ -26,13 +26,23 @@ where
 
 impl Feed for Scope {
     fn feed(&mut self, token: &Token) -> Result<(), RuntimeError> {
+        self.execute_block(token)?;
+        self.cleanup_temp();
+        Ok(())
+    }
+}
+
+impl Scope {
+    // Executes every triple of the block, leaving temporaries for the caller to clean up
+    fn execute_block(&mut self, token: &Token) -> Result<(), RuntimeError> {
         match token {
             Token::Block(block) => {
                 if block.len() % 3 != 0 {
                     return Err(RuntimeError::new(format!(
                         "[Execute] Executed block length must be a multiple of 3, but got {}",
                         block.len()
-                    )));
+                    ))
+                    .with_span(block.span(block.len() - block.len() % 3)));
                 }
                 let mut i = 0;
                 while i < block.len() {
@@ -44,20 +54,21 @@ impl Feed for Scope {
                                 "[Execute] Expected operator at block position {}, but got {}",
                                 i + 1,
                                 &block.data()[i + 1]
-                            )))
+                            ))
+                            .with_span(block.span(i + 1)))
                         }
                     };
                     let rhs = &block.data()[i + 2];
-                    let result = self.execute(lhs, *op, rhs)?;
-                    if match &*result.borrow() {
-                        Value::Null => false,
-                        _ => true,
-                    } {
+                    let span = match (block.span(i), block.span(i + 2)) {
+                        (Some(begin), Some(end)) => Some(begin.to(&end)),
+                        (begin, end) => begin.or(end),
+                    };
+                    let result = self.execute(lhs, op, rhs).map_err(|e| e.with_span(span))?;
+                    if !matches!(&*result.borrow(), Value::Null) {
                         self.set_value(&[RETURN.to_string()], result)?;
                     }
                     i += 3;
                 }
-                self.cleanup_temp();
                 Ok(())
             }
             _ => Err(RuntimeError::new(format!(
@@ -66,4 +77,14 @@ impl Feed for Scope {
             ))),
         }
     }
+
+    // Feeds every triple as soon as it is tokenized, rather than parsing the whole input first
+    // Temporaries are kept until the input ends, as if it had been fed as one block
+    pub fn feed_stream(&mut self, tokens: &mut impl Tokenize) -> Result<(), RuntimeError> {
+        while let Some(triple) = tokens.next_triple() {
+            self.execute_block(&triple?)?;
+        }
+        self.cleanup_temp();
+        Ok(())
+    }
 }
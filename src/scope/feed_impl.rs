@@ -35,10 +35,23 @@ impl Scope {
     }
 
     fn do_call(&mut self, lhs: &Token, rhs: &Token) -> Result<ValueRc, RuntimeError> {
-        self.as_feedable(lhs)?
-            .borrow_mut()
-            .call(&mut *self.to_subscope(rhs)?.borrow_mut())
-            .map_err(|e| RuntimeError::new(format!("[Call] When calling {},\n{}", lhs, e)))
+        // Functors are taken out of their values before being called, so they may be called
+        // again while running, e.g. by an imported file
+        let functor = match lhs {
+            Token::Symbol(name) => match &*self.query_value(name)?.borrow() {
+                Value::Functor(functor) => Some(functor.clone()),
+                _ => None,
+            },
+            _ => None,
+        };
+        let result = match functor {
+            Some(functor) => functor.call(&mut self.to_subscope(rhs)?.borrow_mut()),
+            None => self
+                .as_feedable(lhs)?
+                .borrow_mut()
+                .call(&mut self.to_subscope(rhs)?.borrow_mut()),
+        };
+        result.map_err(|e| RuntimeError::new(format!("[Call] When calling {},\n{}", lhs, e)))
     }
 
     fn do_link(&mut self, lhs: &Token, rhs: &Token) -> Result<(), RuntimeError> {
@@ -69,23 +82,48 @@ impl Scope {
         }
     }
 
+    fn do_default(&mut self, lhs: &Token, rhs: &Token) -> Result<(), RuntimeError> {
+        match lhs {
+            Token::Symbol(name) => {
+                if let Value::Null = *self.query_value(name)?.borrow() {
+                    let value = self.as_true_value(rhs)?;
+                    self.set_value(name, value)?;
+                }
+                Ok(())
+            }
+            _ => Err(RuntimeError::new(format!(
+                "[Default] Expected symbol at left hand side, but got {}",
+                lhs
+            ))),
+        }
+    }
+
     pub(super) fn execute(
         &mut self,
         lhs: &Token,
-        op: char,
+        op: &str,
         rhs: &Token,
     ) -> Result<ValueRc, RuntimeError> {
+        // Operators registered by the host take precedence over built-in ones
+        let handler = self.registry().borrow().get_operator(op);
+        if let Some(handler) = handler {
+            return handler(self, lhs, rhs);
+        }
         match op {
-            ':' => self.do_feed(lhs, rhs),
-            '=' => {
+            ":" => self.do_feed(lhs, rhs),
+            "=" => {
                 self.do_assign(lhs, rhs)?;
                 Ok(rc_cell(Value::Null))
             }
-            '!' => self.do_call(lhs, rhs),
-            '~' => {
+            "!" => self.do_call(lhs, rhs),
+            "~" => {
                 self.do_link(lhs, rhs)?;
                 Ok(rc_cell(Value::Null))
             }
+            "?=" => {
+                self.do_default(lhs, rhs)?;
+                Ok(rc_cell(Value::Null))
+            }
             _ => Err(RuntimeError::new(format!(
                 "[Execute] Operator \'{}\' is not supported",
                 op
@@ -0,0 +1,143 @@
+use crate::prelude::*;
+use std::path::{Component, Path, PathBuf};
+
+/// Finds and reads the files imported by scripts
+pub trait Resolver {
+    // Turns a path written in a file into the path of the file it refers to, by default relative
+    // to the directory of the importing file, or as it is when imported by the host
+    fn resolve(&self, from: Option<&Path>, path: &str) -> PathBuf {
+        let joined = match from.and_then(|from| from.parent()) {
+            Some(dir) => dir.join(path),
+            None => PathBuf::from(path),
+        };
+        normalize(&joined)
+    }
+
+    fn read(&self, path: &Path) -> std::io::Result<String>;
+}
+
+// Removes . and .. from a path without touching the file system, so the same file is always
+// known by the same path
+fn normalize(path: &Path) -> PathBuf {
+    let mut result = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if matches!(result.components().next_back(), Some(Component::Normal(_))) {
+                    result.pop();
+                } else {
+                    result.push(component);
+                }
+            }
+            _ => result.push(component),
+        }
+    }
+    result
+}
+
+/// Reads imported files from the file system
+#[derive(Debug, Default)]
+pub struct FsResolver;
+
+impl Resolver for FsResolver {
+    fn read(&self, path: &Path) -> std::io::Result<String> {
+        std::fs::read_to_string(path)
+    }
+}
+
+/// Serves imported files from memory, e.g. in tests
+#[derive(Debug, Default)]
+pub struct MemoryResolver {
+    files: HashMap<PathBuf, String>,
+}
+
+impl MemoryResolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, path: impl AsRef<Path>, source: impl Into<String>) {
+        self.files.insert(normalize(path.as_ref()), source.into());
+    }
+}
+
+impl Resolver for MemoryResolver {
+    fn read(&self, path: &Path) -> std::io::Result<String> {
+        self.files.get(path).cloned().ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::NotFound, "No such file in memory")
+        })
+    }
+}
+
+impl Scope {
+    // Makes imports read files through the resolver, in this scope and every scope created from it
+    pub fn set_resolver(&mut self, resolver: impl Resolver + 'static) {
+        self.registry().borrow_mut().set_resolver(resolver);
+    }
+
+    // Makes imports parse files with the syntax, which should be the one the host parses with,
+    // so its operators and decorators may be used in imported files as well
+    pub fn set_syntax(&mut self, syntax: Syntax) {
+        self.registry().borrow_mut().set_syntax(syntax);
+    }
+
+    // Parses a file, or takes it from the cache if it was loaded before
+    fn load_file(&self, path: &str) -> Result<(PathBuf, Rc<Token>), RuntimeError> {
+        let (resolver, path) = {
+            let registry = self.registry().borrow();
+            let resolver = registry.resolver();
+            let path = resolver.resolve(registry.loading().last().map(|p| p.as_path()), path);
+            if let Some(token) = registry.cached(&path) {
+                return Ok((path, token));
+            }
+            (resolver, path)
+        };
+        let source = resolver.read(&path).map_err(|e| {
+            RuntimeError::new(format!("[Import] Cannot read {}: {}", path.display(), e))
+        })?;
+        let file = self.registry().borrow_mut().add_file(path.clone());
+        let mut parser = Parser::with_file(source, file);
+        parser.set_syntax(self.registry().borrow().syntax().clone());
+        let token = match parser.parse() {
+            Ok(token) => Rc::new(token),
+            Err(e) => {
+                // Nothing else is loaded while parsing, so the file added is the last one
+                self.registry().borrow_mut().pop_file();
+                return Err(RuntimeError::new(format!(
+                    "[Import] In {},\n{}",
+                    path.display(),
+                    e
+                )));
+            }
+        };
+        self.registry()
+            .borrow_mut()
+            .cache(path.clone(), token.clone());
+        Ok((path, token))
+    }
+
+    // Feeds a file into this scope, resolving the path relative to the file being imported, if any
+    pub fn import(&mut self, path: &str) -> Result<(), RuntimeError> {
+        let (path, token) = self.load_file(path)?;
+        {
+            let mut registry = self.registry().borrow_mut();
+            if let Some(begin) = registry.loading().iter().position(|p| *p == path) {
+                let cycle = registry.loading()[begin..]
+                    .iter()
+                    .chain(std::iter::once(&path))
+                    .map(|p| p.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(" -> ");
+                return Err(RuntimeError::new(format!(
+                    "[Import] Cycle detected: {}",
+                    cycle
+                )));
+            }
+            registry.loading_mut().push(path.clone());
+        }
+        let result = self.feed(&token);
+        self.registry().borrow_mut().loading_mut().pop();
+        result.map_err(|e| RuntimeError::new(format!("[Import] In {},\n{}", path.display(), e)))
+    }
+}
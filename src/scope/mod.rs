@@ -1,7 +1,11 @@
 mod access;
 mod feed;
 mod feed_impl;
+mod import;
+mod registry;
 mod scope;
 
 pub use feed::Feed;
+pub use import::{FsResolver, MemoryResolver, Resolver};
+pub use registry::{DecoratorHook, OperatorInner, Registry};
 pub use scope::{Scope, ScopeMode, ScopeRc};
@@ -0,0 +1,148 @@
+use crate::prelude::*;
+use std::path::{Path, PathBuf};
+
+pub trait OperatorInner: Fn(&mut Scope, &Token, &Token) -> Result<ValueRc, RuntimeError> {}
+
+impl<F: Fn(&mut Scope, &Token, &Token) -> Result<ValueRc, RuntimeError>> OperatorInner for F {}
+
+/// Controls how blocks with a custom decorator are interpreted, each method returning None to
+/// treat the block as if it had no decorator
+pub trait DecoratorHook {
+    // Used when the block is evaluated as a value, e.g. at the right hand side of =
+    fn as_value(
+        &self,
+        _scope: &mut Scope,
+        _block: &BlockVec,
+    ) -> Option<Result<ValueRc, RuntimeError>> {
+        None
+    }
+
+    // Used when the block is evaluated as an independent scope, e.g. at the right hand side of :
+    fn to_scope(
+        &self,
+        _scope: &mut Scope,
+        _block: &BlockVec,
+    ) -> Option<Result<ScopeRc, RuntimeError>> {
+        None
+    }
+
+    // Used when the block is evaluated as a sub-scope, e.g. as an argument of !
+    fn to_subscope(
+        &self,
+        _scope: &mut Scope,
+        _block: &BlockVec,
+    ) -> Option<Result<ScopeRc, RuntimeError>> {
+        None
+    }
+}
+
+/// Extensions registered by the host, shared by a scope and every scope created from it
+#[derive(Default)]
+pub struct Registry {
+    operators: HashMap<String, Rc<dyn OperatorInner>>,
+    decorators: HashMap<String, Rc<dyn DecoratorHook>>,
+
+    resolver: Option<Rc<dyn Resolver>>,
+    // The syntax imported files are parsed with
+    syntax: Syntax,
+    // Imported files, the id of each being its index plus one, as 0 is the source of the host
+    files: Vec<PathBuf>,
+    cache: HashMap<PathBuf, Rc<Token>>,
+    // Files being imported, the innermost last
+    loading: Vec<PathBuf>,
+}
+
+impl Debug for Registry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("Registry")
+            .field("operators", &self.operators.keys().collect::<Vec<_>>())
+            .field("decorators", &self.decorators.keys().collect::<Vec<_>>())
+            .field("syntax", &self.syntax)
+            .field("files", &self.files)
+            .field("loading", &self.loading)
+            .finish()
+    }
+}
+
+impl Registry {
+    pub fn insert_operator(&mut self, op: &str, handler: impl OperatorInner + 'static) {
+        self.operators.insert(op.to_string(), Rc::new(handler));
+    }
+
+    pub fn get_operator(&self, op: &str) -> Option<Rc<dyn OperatorInner>> {
+        self.operators.get(op).cloned()
+    }
+
+    pub fn insert_decorator(&mut self, keyword: &str, hook: impl DecoratorHook + 'static) {
+        self.decorators.insert(keyword.to_string(), Rc::new(hook));
+    }
+
+    pub fn get_decorator(&self, keyword: &str) -> Option<Rc<dyn DecoratorHook>> {
+        self.decorators.get(keyword).cloned()
+    }
+
+    // Whether nothing was registered or loaded yet
+    pub(crate) fn is_empty(&self) -> bool {
+        self.operators.is_empty()
+            && self.decorators.is_empty()
+            && self.resolver.is_none()
+            && self.syntax == Syntax::default()
+            && self.files.is_empty()
+            && self.cache.is_empty()
+    }
+
+    pub fn set_resolver(&mut self, resolver: impl Resolver + 'static) {
+        self.resolver = Some(Rc::new(resolver));
+    }
+
+    pub fn resolver(&self) -> Rc<dyn Resolver> {
+        match &self.resolver {
+            Some(resolver) => resolver.clone(),
+            None => Rc::new(FsResolver),
+        }
+    }
+
+    pub fn syntax(&self) -> &Syntax {
+        &self.syntax
+    }
+
+    // Files parsed with the old syntax may parse differently now, so they are parsed again
+    pub fn set_syntax(&mut self, syntax: Syntax) {
+        if syntax != self.syntax {
+            self.cache.clear();
+        }
+        self.syntax = syntax;
+    }
+
+    pub(crate) fn add_file(&mut self, path: PathBuf) -> usize {
+        self.files.push(path);
+        self.files.len()
+    }
+
+    pub(crate) fn pop_file(&mut self) {
+        self.files.pop();
+    }
+
+    // The path of the file with the id found in spans
+    pub fn file_path(&self, file: usize) -> Option<&Path> {
+        file.checked_sub(1)
+            .and_then(|i| self.files.get(i))
+            .map(|path| path.as_path())
+    }
+
+    pub(crate) fn cached(&self, path: &Path) -> Option<Rc<Token>> {
+        self.cache.get(path).cloned()
+    }
+
+    pub(crate) fn cache(&mut self, path: PathBuf, token: Rc<Token>) {
+        self.cache.insert(path, token);
+    }
+
+    pub(crate) fn loading(&self) -> &Vec<PathBuf> {
+        &self.loading
+    }
+
+    pub(crate) fn loading_mut(&mut self) -> &mut Vec<PathBuf> {
+        &mut self.loading
+    }
+}
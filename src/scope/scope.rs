@@ -2,17 +2,11 @@ use crate::prelude::*;
 
 pub type ScopeRc = RcCell<Scope>;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Default)]
 pub struct ScopeMode {
     pub filter: bool,
 }
 
-impl Default for ScopeMode {
-    fn default() -> Self {
-        Self { filter: false }
-    }
-}
-
 #[derive(Debug)]
 pub struct Scope {
     values: HashMap<String, ValueRc>,
@@ -24,6 +18,8 @@ pub struct Scope {
     depth: usize,
 
     pub mode: ScopeMode,
+
+    registry: RcCell<Registry>,
 }
 
 impl Clone for Scope {
@@ -34,10 +30,61 @@ impl Clone for Scope {
             parent: std::ptr::null_mut(),
             depth: self.depth,
             mode: self.mode,
+            registry: self.registry.clone(),
         }
     }
 }
 
+// Scopes are equal if they hold equal values and scopes, wherever they are placed
+impl PartialEq for Scope {
+    fn eq(&self, other: &Self) -> bool {
+        self.values.len() == other.values.len()
+            && self.scopes.len() == other.scopes.len()
+            && self.values.iter().all(|(name, value)| {
+                other
+                    .values
+                    .get(name)
+                    .is_some_and(|other| *value.borrow() == *other.borrow())
+            })
+            && self.scopes.iter().all(|(name, scope)| {
+                other.scopes.get(name).is_some_and(|other| {
+                    Rc::ptr_eq(scope, other) || *scope.borrow() == *other.borrow()
+                })
+            })
+    }
+}
+
+// Scopes are not ordered, they only compare as equal or not
+impl PartialOrd for Scope {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        if self == other {
+            Some(std::cmp::Ordering::Equal)
+        } else {
+            None
+        }
+    }
+}
+
+// Children may outlive their parent, e.g. when kept as scope values, so they are cut off
+// from it rather than left pointing to freed memory
+impl Drop for Scope {
+    fn drop(&mut self) {
+        for child in self.scopes.values() {
+            if let Ok(mut child) = child.try_borrow_mut() {
+                if std::ptr::eq(child.parent, self) {
+                    child.parent = std::ptr::null_mut();
+                }
+            }
+        }
+    }
+}
+
+impl Default for Scope {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Scope {
     pub fn new() -> Self {
         Self {
@@ -46,6 +93,48 @@ impl Scope {
             parent: std::ptr::null_mut(),
             depth: 0,
             mode: Default::default(),
+            registry: Default::default(),
+        }
+    }
+
+    // Creates a scope without a parent, sharing the registry of self
+    pub(crate) fn new_indep(&self) -> Self {
+        let mut scope = Self::new();
+        scope.registry = self.registry.clone();
+        scope
+    }
+
+    pub fn registry(&self) -> &RcCell<Registry> {
+        &self.registry
+    }
+
+    // Handles lhs op rhs with the given closure in this scope and every scope created from it
+    pub fn register_operator(&mut self, op: &str, handler: impl OperatorInner + 'static) {
+        self.registry.borrow_mut().insert_operator(op, handler);
+    }
+
+    // Interprets blocks decorated with the keyword by the hook, the keyword must also be listed
+    // in Syntax::decorators for the parser to accept it
+    pub fn register_decorator(&mut self, keyword: &str, hook: impl DecoratorHook + 'static) {
+        self.registry.borrow_mut().insert_decorator(keyword, hook);
+    }
+
+    // Finds the hook of a custom decorator
+    pub(crate) fn decorator_hook(
+        &self,
+        decor: &BlockDecorator,
+    ) -> Result<Option<Rc<dyn DecoratorHook>>, RuntimeError> {
+        match decor {
+            BlockDecorator::Custom(keyword) => {
+                match self.registry.borrow().get_decorator(keyword) {
+                    Some(hook) => Ok(Some(hook)),
+                    None => Err(RuntimeError::new(format!(
+                        "[Decorator] No hook registered for decorator {}",
+                        keyword
+                    ))),
+                }
+            }
+            _ => Ok(None),
         }
     }
 
@@ -79,7 +168,111 @@ impl Scope {
         }
     }
 
+    // Finds a list, map or scope value that the path goes through, for paths like
+    // servers.0.port, returning it along with the rest of the path and the sub-scope holding
+    // it, or None if found in self or an ancestor
+    // Sub-scopes come first, so a value is only indexed if no sub-scope has its name
+    #[allow(clippy::type_complexity)]
+    fn find_indexed<'a>(
+        &self,
+        name: &'a [String],
+    ) -> Option<(ValueRc, &'a [String], Option<ScopeRc>)> {
+        let mut scope: Option<ScopeRc> = None;
+        for i in 0..name.len() - 1 {
+            // Only the first name is searched in ancestor scopes
+            let next = match &scope {
+                None => self.get_scope(&name[i]),
+                Some(scope) => scope.borrow().scopes.get(&name[i]).cloned(),
+            };
+            if next.is_some() {
+                scope = next;
+                continue;
+            }
+            let value = match &scope {
+                None => self.get_value(&name[i]),
+                Some(scope) => scope.borrow().values.get(&name[i]).cloned(),
+            }?;
+            let indexed = matches!(
+                &*value.borrow(),
+                Value::List(_) | Value::Map(_) | Value::Scope(_)
+            );
+            return indexed.then(|| (value, &name[i + 1..], scope));
+        }
+        None
+    }
+
+    // Whether the value reaches self or one of its ancestors, which would then own itself and
+    // never be freed if the value were stored in self
+    fn is_held_by(&self, value: &Value) -> bool {
+        if !matches!(value, Value::List(_) | Value::Map(_) | Value::Scope(_)) {
+            return false;
+        }
+        let mut current = Some(self);
+        while let Some(s) = current {
+            if value.holds(s as *const Scope as *const ()) {
+                return true;
+            }
+            current = s.get_parent().map(|p| &*p);
+        }
+        false
+    }
+
+    fn check_held(&self, name: &[String], value: &ValueRc) -> Result<(), RuntimeError> {
+        if self.is_held_by(&value.borrow()) {
+            return Err(RuntimeError::new(format!(
+                "[Scope] {} holds a scope it is set in, which cannot be stored, otherwise it will cause memory leaks",
+                name.join(".")
+            )));
+        }
+        Ok(())
+    }
+
+    // Follows the rest of a path into a value, an index for lists and a key for maps
+    fn index_value(value: ValueRc, name: &[String]) -> Result<ValueRc, RuntimeError> {
+        if name.is_empty() {
+            return Ok(value);
+        }
+        let next = match &*value.borrow() {
+            Value::List(list) => {
+                let item = name[0].parse::<usize>().ok().and_then(|i| list.get(i));
+                match item {
+                    Some(item) => item.clone(),
+                    None => {
+                        return Err(RuntimeError::new(format!(
+                            "[Scope] Expected an index below {} into list, but got {}",
+                            list.len(),
+                            name[0]
+                        )))
+                    }
+                }
+            }
+            Value::Map(map) => match map.get(&name[0]) {
+                Some(item) => item.clone(),
+                None => {
+                    return Err(RuntimeError::new(format!(
+                        "[Scope] No key {} in a map of {} entries",
+                        name[0],
+                        map.len()
+                    )))
+                }
+            },
+            Value::Scope(scope) => return scope.borrow_mut().query_value(name),
+            value => {
+                return Err(RuntimeError::new(format!(
+                    "[Scope] Cannot look up {} in {}",
+                    name[0], value
+                )))
+            }
+        };
+        Self::index_value(next, &name[1..])
+    }
+
     pub fn query_value(&mut self, name: &[String]) -> Result<ValueRc, RuntimeError> {
+        if name.len() > 1 {
+            if let Some((value, rest, _)) = self.find_indexed(name) {
+                return Self::index_value(value, rest);
+            }
+        }
         match name.len() {
             0 => Err(RuntimeError::new(format!(
                 "[Scope] Expected at least one name, but got {}",
@@ -94,23 +287,68 @@ impl Scope {
     }
 
     pub fn set_value(&mut self, name: &[String], value: ValueRc) -> Result<(), RuntimeError> {
+        if name.len() > 1 {
+            if let Some((container, rest, owner)) = self.find_indexed(name) {
+                match owner {
+                    Some(owner) => owner.borrow().check_held(name, &value)?,
+                    None => self.check_held(name, &value)?,
+                }
+                let (last, rest) = rest.split_last().unwrap();
+                let container = Self::index_value(container, rest)?;
+                if Rc::ptr_eq(&container, &value)
+                    || value.borrow().holds(container.as_ptr() as *const ())
+                {
+                    return Err(RuntimeError::new(format!(
+                        "[Scope] {} holds the value it is set in, which cannot be stored, otherwise it will cause memory leaks",
+                        name.join(".")
+                    )));
+                }
+                let mut container = container.borrow_mut();
+                return match &mut *container {
+                    Value::List(list) => {
+                        let len = list.len();
+                        match last.parse::<usize>().ok().and_then(|i| list.get_mut(i)) {
+                            Some(item) => {
+                                *item = value;
+                                Ok(())
+                            }
+                            None => Err(RuntimeError::new(format!(
+                                "[Scope] Expected an index below {} into list, but got {}",
+                                len, last
+                            ))),
+                        }
+                    }
+                    Value::Map(map) => {
+                        map.insert(last.clone(), value);
+                        Ok(())
+                    }
+                    Value::Scope(scope) => scope
+                        .borrow_mut()
+                        .set_value(std::slice::from_ref(last), value),
+                    container => Err(RuntimeError::new(format!(
+                        "[Scope] Cannot set {} in {}",
+                        last, container
+                    ))),
+                };
+            }
+        }
         match name.len() {
             0 => Ok(()),
             1 => {
+                self.check_held(name, &value)?;
                 self.values.insert(name[0].clone(), value);
                 Ok(())
             }
             _ => {
-                self.query_scope(&name[0..name.len() - 1])?
-                    .try_borrow_mut()
-                    .map_err(|_| {
-                        RuntimeError::new(format!(
-                            "[Scope] Setting the value of {} and so making it mutable, is illegal",
-                            name.join(".")
-                        ))
-                    })?
-                    .values
-                    .insert(name[name.len() - 1].clone(), value);
+                let scope = self.query_scope(&name[0..name.len() - 1])?;
+                let mut scope = scope.try_borrow_mut().map_err(|_| {
+                    RuntimeError::new(format!(
+                        "[Scope] Setting the value of {} and so making it mutable, is illegal",
+                        name.join(".")
+                    ))
+                })?;
+                scope.check_held(name, &value)?;
+                scope.values.insert(name[name.len() - 1].clone(), value);
                 Ok(())
             }
         }
@@ -228,6 +466,7 @@ impl Scope {
         Ok(())
     }
 
+    #[allow(clippy::mut_from_ref)]
     pub fn get_parent(&self) -> Option<&mut Scope> {
         if self.parent.is_null() {
             None
@@ -244,7 +483,7 @@ impl Scope {
         let mut parent = other.get_parent();
         while let Some(p) = parent {
             if p.depth == self.depth {
-                return p as *const Scope == self as *const Scope;
+                return std::ptr::eq(p, self);
             }
             parent = p.get_parent();
         }
@@ -253,15 +492,19 @@ impl Scope {
 
     pub fn link_child(&mut self, name: &str, child: ScopeRc) -> Result<(), RuntimeError> {
         if child.borrow().is_parent_of(self) {
-            return Err(RuntimeError::new(format!(
+            Err(RuntimeError::new(format!(
                 "[Scope] {} and {} are related and cannot be linked, otherwise it will cause memory leaks",
                 self,
                 child.borrow()
-            )));
+            )))
         } else if child.borrow().parent.is_null() {
             child.borrow_mut().parent = self;
             child.borrow_mut().depth = self.depth + 1;
             child.borrow_mut().mode = self.mode;
+            // A child keeps the extensions it was given, and only takes those of self if it has none
+            if child.borrow().registry.borrow().is_empty() {
+                child.borrow_mut().inherit_registry(&self.registry);
+            }
             self.scopes.insert(name.to_string(), child);
             Ok(())
         } else {
@@ -272,6 +515,28 @@ impl Scope {
         }
     }
 
+    // Uses the given registry in this scope and the children sharing its own
+    fn inherit_registry(&mut self, registry: &RcCell<Registry>) {
+        let own = std::mem::replace(&mut self.registry, registry.clone());
+        for scope in self.scopes.values() {
+            if Rc::ptr_eq(&scope.borrow().registry, &own) {
+                scope.borrow_mut().inherit_registry(registry);
+            }
+        }
+    }
+
+    // Cuts an anonymous child off from this scope, so it may outlive it as a value
+    pub(crate) fn detach_anonymous(&mut self, child: &ScopeRc) {
+        if self
+            .scopes
+            .get(ANONYMOUS)
+            .is_some_and(|anonymous| Rc::ptr_eq(anonymous, child))
+        {
+            self.scopes.remove(ANONYMOUS);
+        }
+        child.borrow_mut().parent = std::ptr::null_mut();
+    }
+
     pub(crate) fn get_values(&self) -> &HashMap<String, ValueRc> {
         &self.values
     }
@@ -280,6 +545,37 @@ impl Scope {
         &self.scopes
     }
 
+    // The values and sub-scopes by name, leaving out functors and anonymous scopes
+    // A name used by both a value and a sub-scope cannot be one entry, and fails
+    pub(crate) fn entries(&self) -> Result<Vec<(String, Value)>, RuntimeError> {
+        let mut entries: Vec<(String, Value)> = self
+            .values
+            .iter()
+            .filter(|(_, value)| !matches!(*value.borrow(), Value::Functor(_)))
+            .map(|(name, value)| (name.clone(), value.borrow().clone()))
+            .collect();
+        entries.extend(
+            self.scopes
+                .iter()
+                .filter(|(name, _)| *name != ANONYMOUS)
+                .map(|(name, scope)| (name.clone(), Value::Scope(scope.clone()))),
+        );
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        if let Some(pair) = entries.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+            return Err(RuntimeError::new(format!(
+                "[Scope] {} names both a value and a scope, which cannot be told apart as entries",
+                pair[0].0
+            )));
+        }
+        Ok(entries)
+    }
+
+    // Drops both the value and the sub-scope of the name, if any
+    pub(crate) fn remove_entry(&mut self, name: &str) {
+        self.values.remove(name);
+        self.scopes.remove(name);
+    }
+
     pub(crate) fn cleanup_temp(&mut self) {
         if self.mode.filter {
             let _ = self.scopes.remove(ANONYMOUS);
@@ -295,15 +591,7 @@ impl Scope {
 
             let mut to_remove = Vec::new();
             for (k, v) in self.values.iter() {
-                let mut flag = false;
-                match &*v.borrow() {
-                    Value::Functor(_) => flag = true,
-                    _ => {}
-                }
-                if let Some('_') = k.chars().nth(0) {
-                    flag = true;
-                }
-                if flag {
+                if matches!(&*v.borrow(), Value::Functor(_)) || k.starts_with('_') {
                     to_remove.push(k.clone());
                 }
             }
@@ -0,0 +1,399 @@
+use crate::prelude::*;
+use serde::de::{
+    self, DeserializeOwned, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, SeqAccess,
+    VariantAccess, Visitor,
+};
+
+struct Deserializer {
+    value: Value,
+}
+
+impl Deserializer {
+    fn error(&self, msg: String) -> RuntimeError {
+        RuntimeError::new(format!("[Serde] {}", msg))
+    }
+
+    fn expected(&self, expected: &str) -> RuntimeError {
+        self.error(format!("expected {}, got {}", expected, self.value.kind()))
+    }
+
+    // The entries of a value read as a map, a duration giving the fields of std::time::Duration
+    fn entries(&self) -> Result<Option<Vec<(String, Value)>>, RuntimeError> {
+        Ok(match &self.value {
+            Value::Map(map) => Some(
+                map.iter()
+                    .map(|(key, value)| (key.clone(), value.borrow().clone()))
+                    .collect(),
+            ),
+            Value::Scope(scope) => Some(scope.borrow().entries()?),
+            Value::Duration(d) => Some(vec![
+                ("secs".to_string(), Value::Size(d.as_secs())),
+                ("nanos".to_string(), Value::Int(d.subsec_nanos() as i64)),
+            ]),
+            _ => None,
+        })
+    }
+
+    fn int<'de, V: Visitor<'de>>(&self, visitor: V) -> Result<V::Value, RuntimeError> {
+        match &self.value {
+            Value::Int(i) => visitor.visit_i64(*i),
+            Value::Size(s) => visitor.visit_u64(*s),
+            _ => Err(self.expected("Int")),
+        }
+    }
+
+    fn float<'de, V: Visitor<'de>>(&self, visitor: V) -> Result<V::Value, RuntimeError> {
+        match &self.value {
+            Value::Float(f) => visitor.visit_f64(*f),
+            Value::Int(i) => visitor.visit_f64(*i as f64),
+            _ => Err(self.expected("Float")),
+        }
+    }
+
+    fn seq<'de, V: Visitor<'de>>(&self, visitor: V) -> Result<V::Value, RuntimeError> {
+        match &self.value {
+            Value::List(list) => {
+                let items: Vec<Value> = list.iter().map(|item| item.borrow().clone()).collect();
+                visitor.visit_seq(Seq {
+                    items: items.into_iter().enumerate(),
+                })
+            }
+            _ => Err(self.expected("List")),
+        }
+    }
+
+    fn map<'de, V: Visitor<'de>>(
+        &self,
+        visitor: V,
+        expected: &str,
+    ) -> Result<V::Value, RuntimeError> {
+        match self.entries()? {
+            Some(entries) => visitor.visit_map(Entries {
+                entries: entries.into_iter(),
+                value: None,
+            }),
+            None => Err(self.expected(expected)),
+        }
+    }
+}
+
+macro_rules! deserialize_int {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, RuntimeError> {
+                self.int(visitor)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for &Deserializer {
+    type Error = RuntimeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, RuntimeError> {
+        match &self.value {
+            Value::Null => visitor.visit_unit(),
+            Value::Bool(b) => visitor.visit_bool(*b),
+            Value::Int(i) => visitor.visit_i64(*i),
+            Value::Size(s) => visitor.visit_u64(*s),
+            Value::Float(f) => visitor.visit_f64(*f),
+            Value::Str(s) => visitor.visit_str(s),
+            Value::Bytes(b) => visitor.visit_bytes(b),
+            Value::List(_) => self.seq(visitor),
+            Value::Map(_) | Value::Scope(_) | Value::Duration(_) => self.map(visitor, "Map"),
+            Value::Functor(_) => Err(self.expected("a value")),
+        }
+    }
+
+    deserialize_int! {
+        deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64 deserialize_i128
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64 deserialize_u128
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, RuntimeError> {
+        self.float(visitor)
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, RuntimeError> {
+        self.float(visitor)
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, RuntimeError> {
+        match &self.value {
+            Value::Bool(b) => visitor.visit_bool(*b),
+            _ => Err(self.expected("Bool")),
+        }
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, RuntimeError> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, RuntimeError> {
+        match &self.value {
+            Value::Str(s) => visitor.visit_str(s),
+            _ => Err(self.expected("Str")),
+        }
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, RuntimeError> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, RuntimeError> {
+        match &self.value {
+            Value::Bytes(b) => visitor.visit_bytes(b),
+            _ => Err(self.expected("Bytes")),
+        }
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, RuntimeError> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, RuntimeError> {
+        match &self.value {
+            Value::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, RuntimeError> {
+        match &self.value {
+            Value::Null => visitor.visit_unit(),
+            _ => Err(self.expected("Null")),
+        }
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, RuntimeError> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, RuntimeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, RuntimeError> {
+        self.seq(visitor)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, RuntimeError> {
+        self.seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, RuntimeError> {
+        self.seq(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, RuntimeError> {
+        self.map(visitor, "Map")
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, RuntimeError> {
+        self.map(visitor, "Scope")
+    }
+
+    // A unit variant is written as its name, any other as a map or scope holding only it
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, RuntimeError> {
+        if let Value::Str(s) = &self.value {
+            let variant: de::value::StrDeserializer<RuntimeError> = s.as_str().into_deserializer();
+            return visitor.visit_enum(variant);
+        }
+        match self.entries()? {
+            Some(mut entries) if entries.len() == 1 => {
+                let (name, value) = entries.pop().unwrap();
+                visitor.visit_enum(Enum {
+                    name,
+                    value: Deserializer { value },
+                })
+            }
+            _ => Err(self.expected("Str or a Map of one variant")),
+        }
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, RuntimeError> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, RuntimeError> {
+        visitor.visit_unit()
+    }
+}
+
+struct Seq {
+    items: std::iter::Enumerate<std::vec::IntoIter<Value>>,
+}
+
+impl<'de> SeqAccess<'de> for Seq {
+    type Error = RuntimeError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, RuntimeError> {
+        match self.items.next() {
+            Some((i, item)) => seed
+                .deserialize(&Deserializer { value: item })
+                .map(Some)
+                .map_err(|e| e.within(&i.to_string())),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.items.len())
+    }
+}
+
+struct Entries {
+    entries: std::vec::IntoIter<(String, Value)>,
+    // The entry whose key was just read
+    value: Option<(String, Value)>,
+}
+
+impl<'de> MapAccess<'de> for Entries {
+    type Error = RuntimeError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, RuntimeError> {
+        match self.entries.next() {
+            Some((key, value)) => {
+                let result = seed.deserialize(key.as_str().into_deserializer());
+                self.value = Some((key, value));
+                result.map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, RuntimeError> {
+        let (key, value) = self.value.take().unwrap();
+        seed.deserialize(&Deserializer { value })
+            .map_err(|e| e.within(&key))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+}
+
+struct Enum {
+    name: String,
+    value: Deserializer,
+}
+
+// The fields of a variant, whose errors are placed at its name
+struct Variant {
+    name: String,
+    value: Deserializer,
+}
+
+impl<'de> EnumAccess<'de> for Enum {
+    type Error = RuntimeError;
+    type Variant = Variant;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Variant), RuntimeError> {
+        let name: de::value::StrDeserializer<RuntimeError> = self.name.as_str().into_deserializer();
+        let variant = seed.deserialize(name)?;
+        Ok((
+            variant,
+            Variant {
+                name: self.name,
+                value: self.value,
+            },
+        ))
+    }
+}
+
+impl<'de> VariantAccess<'de> for Variant {
+    type Error = RuntimeError;
+
+    fn unit_variant(self) -> Result<(), RuntimeError> {
+        de::Deserialize::deserialize(&self.value).map_err(|e| e.within(&self.name))
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, RuntimeError> {
+        seed.deserialize(&self.value)
+            .map_err(|e| e.within(&self.name))
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, RuntimeError> {
+        self.value.seq(visitor).map_err(|e| e.within(&self.name))
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, RuntimeError> {
+        self.value
+            .map(visitor, "Scope")
+            .map_err(|e| e.within(&self.name))
+    }
+}
+
+/// Reads a value of type T from the values and sub-scopes of a scope
+///
+/// Structs and maps are read from scopes and map values, sequences from lists, and options
+/// from null or any other value. Errors name the path of the value that failed in
+/// RuntimeError::path
+pub fn from_scope<T: DeserializeOwned>(scope: &Scope) -> Result<T, RuntimeError> {
+    let deserializer = Deserializer {
+        value: Value::Scope(rc_cell(scope.clone())),
+    };
+    T::deserialize(&deserializer)
+}
+
+/// Parses and feeds the source into a scope with the default functors, then reads a T from it
+pub fn from_str<T: DeserializeOwned>(source: &str) -> Result<T, RuntimeError> {
+    let mut scope = Scope::new();
+    init_functor(&mut scope)?;
+    scope.feed(&Parser::new(source).parse()?)?;
+    scope.cleanup();
+    from_scope(&scope)
+}
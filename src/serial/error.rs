@@ -0,0 +1,13 @@
+use crate::prelude::*;
+
+impl serde::de::Error for RuntimeError {
+    fn custom<T: Display>(msg: T) -> Self {
+        RuntimeError::new(format!("[Serde] {}", msg))
+    }
+}
+
+impl serde::ser::Error for RuntimeError {
+    fn custom<T: Display>(msg: T) -> Self {
+        RuntimeError::new(format!("[Serde] {}", msg))
+    }
+}
@@ -0,0 +1,6 @@
+mod de;
+mod error;
+mod ser;
+
+pub use de::{from_scope, from_str};
+pub use ser::to_string;
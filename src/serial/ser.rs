@@ -0,0 +1,366 @@
+use crate::prelude::*;
+use serde::ser::{self, Serialize};
+
+// Serializes into a Value, structs becoming scopes
+struct Serializer;
+
+// Keeps scopes as sub-scopes, so a nested struct is written as a [name] block
+fn insert(scope: &ScopeRc, name: &str, value: Value) -> Result<(), RuntimeError> {
+    let name = [name.to_string()];
+    match value {
+        Value::Scope(child) => scope.borrow_mut().set_scope(&name, child),
+        value => scope.borrow_mut().set_value(&name, rc_cell(value)),
+    }
+}
+
+// Wraps the value of an enum variant in a map holding only it
+fn variant(variant: &'static str, value: Value) -> Value {
+    let mut map = Map::new();
+    map.insert(variant.to_string(), rc_cell(value));
+    Value::Map(map)
+}
+
+impl ser::Serializer for Serializer {
+    type Ok = Value;
+    type Error = RuntimeError;
+    type SerializeSeq = SeqSerializer;
+    type SerializeTuple = SeqSerializer;
+    type SerializeTupleStruct = SeqSerializer;
+    type SerializeTupleVariant = SeqSerializer;
+    type SerializeMap = MapSerializer;
+    type SerializeStruct = StructSerializer;
+    type SerializeStructVariant = StructSerializer;
+
+    fn serialize_bool(self, v: bool) -> Result<Value, RuntimeError> {
+        Ok(Value::Bool(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Value, RuntimeError> {
+        Ok(Value::Int(v as i64))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Value, RuntimeError> {
+        Ok(Value::Int(v as i64))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Value, RuntimeError> {
+        Ok(Value::Int(v as i64))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Value, RuntimeError> {
+        Ok(Value::Int(v))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Value, RuntimeError> {
+        Ok(Value::Int(v as i64))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Value, RuntimeError> {
+        Ok(Value::Int(v as i64))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Value, RuntimeError> {
+        Ok(Value::Int(v as i64))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Value, RuntimeError> {
+        match i64::try_from(v) {
+            Ok(v) => Ok(Value::Int(v)),
+            Err(_) => Err(RuntimeError::new(format!(
+                "[Serde] Integer {} is out of range",
+                v
+            ))),
+        }
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Value, RuntimeError> {
+        Ok(Value::Float(v as f64))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Value, RuntimeError> {
+        Ok(Value::Float(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Value, RuntimeError> {
+        Ok(Value::Str(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Value, RuntimeError> {
+        Ok(Value::Str(v.to_string()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Value, RuntimeError> {
+        Ok(Value::Bytes(v.to_vec()))
+    }
+
+    fn serialize_none(self) -> Result<Value, RuntimeError> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Value, RuntimeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Value, RuntimeError> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Value, RuntimeError> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<Value, RuntimeError> {
+        Ok(Value::Str(variant.to_string()))
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Value, RuntimeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        name: &'static str,
+        value: &T,
+    ) -> Result<Value, RuntimeError> {
+        Ok(variant(name, value.serialize(self)?))
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<SeqSerializer, RuntimeError> {
+        Ok(SeqSerializer {
+            list: VecDeque::new(),
+            variant: None,
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SeqSerializer, RuntimeError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SeqSerializer, RuntimeError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<SeqSerializer, RuntimeError> {
+        Ok(SeqSerializer {
+            list: VecDeque::new(),
+            variant: Some(variant),
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<MapSerializer, RuntimeError> {
+        Ok(MapSerializer {
+            map: Map::new(),
+            key: None,
+        })
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<StructSerializer, RuntimeError> {
+        Ok(StructSerializer {
+            scope: rc_cell(Scope::new()),
+            variant: None,
+        })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<StructSerializer, RuntimeError> {
+        Ok(StructSerializer {
+            scope: rc_cell(Scope::new()),
+            variant: Some(variant),
+        })
+    }
+}
+
+struct SeqSerializer {
+    list: VecDeque<ValueRc>,
+    variant: Option<&'static str>,
+}
+
+impl SeqSerializer {
+    fn push<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), RuntimeError> {
+        self.list.push_back(rc_cell(value.serialize(Serializer)?));
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Value, RuntimeError> {
+        let list = Value::List(self.list);
+        Ok(match self.variant {
+            Some(name) => variant(name, list),
+            None => list,
+        })
+    }
+}
+
+impl ser::SerializeSeq for SeqSerializer {
+    type Ok = Value;
+    type Error = RuntimeError;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), RuntimeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, RuntimeError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for SeqSerializer {
+    type Ok = Value;
+    type Error = RuntimeError;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), RuntimeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, RuntimeError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for SeqSerializer {
+    type Ok = Value;
+    type Error = RuntimeError;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), RuntimeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, RuntimeError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for SeqSerializer {
+    type Ok = Value;
+    type Error = RuntimeError;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), RuntimeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, RuntimeError> {
+        self.finish()
+    }
+}
+
+struct MapSerializer {
+    map: Map,
+    key: Option<String>,
+}
+
+impl ser::SerializeMap for MapSerializer {
+    type Ok = Value;
+    type Error = RuntimeError;
+
+    // Keys are written as strings, so only strings and what displays as a literal may be keys
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), RuntimeError> {
+        self.key = Some(match key.serialize(Serializer)? {
+            Value::Str(key) => key,
+            key @ (Value::Int(_) | Value::Bool(_)) => key.to_string(),
+            key => {
+                return Err(RuntimeError::new(format!(
+                    "[Serde] Map keys must be strings, but got {}",
+                    key
+                )))
+            }
+        });
+        Ok(())
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), RuntimeError> {
+        let key = self.key.take().unwrap();
+        self.map.insert(key, rc_cell(value.serialize(Serializer)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, RuntimeError> {
+        Ok(Value::Map(self.map))
+    }
+}
+
+struct StructSerializer {
+    scope: ScopeRc,
+    variant: Option<&'static str>,
+}
+
+impl ser::SerializeStruct for StructSerializer {
+    type Ok = Value;
+    type Error = RuntimeError;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), RuntimeError> {
+        insert(&self.scope, key, value.serialize(Serializer)?)
+    }
+
+    fn end(self) -> Result<Value, RuntimeError> {
+        Ok(Value::Scope(self.scope))
+    }
+}
+
+impl ser::SerializeStructVariant for StructSerializer {
+    type Ok = Value;
+    type Error = RuntimeError;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), RuntimeError> {
+        insert(&self.scope, key, value.serialize(Serializer)?)
+    }
+
+    fn end(self) -> Result<Value, RuntimeError> {
+        Ok(variant(self.variant.unwrap(), Value::Scope(self.scope)))
+    }
+}
+
+/// Writes a struct or map as IPML source, nested structs becoming sub-scopes
+pub fn to_string<T: ?Sized + Serialize>(value: &T) -> Result<String, RuntimeError> {
+    match value.serialize(Serializer)? {
+        Value::Scope(scope) => scope.borrow().to_ipml_string(),
+        Value::Map(map) => {
+            let scope = rc_cell(Scope::new());
+            for (key, value) in map.iter() {
+                insert(&scope, key, value.borrow().clone())?;
+            }
+            let source = scope.borrow().to_ipml_string();
+            source
+        }
+        value => Err(RuntimeError::new(format!(
+            "[Serde] Expected a struct or map to write as a scope, but got {}",
+            value
+        ))),
+    }
+}
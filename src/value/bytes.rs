@@ -0,0 +1,70 @@
+// Encodings of byte values, as used by hex"..." and b64"..." literals and their functors
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+pub fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// Whitespace between digits is ignored, so long values may be split across lines
+pub fn decode_hex(str: &str) -> Result<Vec<u8>, String> {
+    let digits = str
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| {
+            c.to_digit(16)
+                .map(|d| d as u8)
+                .ok_or_else(|| format!("Invalid hex digit {}", c.escape_debug()))
+        })
+        .collect::<Result<Vec<u8>, String>>()?;
+    if !digits.len().is_multiple_of(2) {
+        return Err("Hex must have an even number of digits".to_string());
+    }
+    Ok(digits.chunks(2).map(|d| d[0] << 4 | d[1]).collect())
+}
+
+// Standard base64 with padding
+pub fn encode_base64(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(BASE64[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+// Padding may be left out, and whitespace is ignored as in hex
+pub fn decode_base64(str: &str) -> Result<Vec<u8>, String> {
+    let chars: Vec<char> = str.chars().filter(|c| !c.is_whitespace()).collect();
+    let data = chars.len() - chars.iter().rev().take_while(|c| **c == '=').count();
+    if chars.len() - data > 2 || !chars.len().is_multiple_of(4) && data != chars.len() {
+        return Err("Invalid base64 padding".to_string());
+    }
+    let mut out = Vec::with_capacity(data * 3 / 4);
+    for chunk in chars[..data].chunks(4) {
+        if chunk.len() == 1 {
+            return Err("Invalid base64 length".to_string());
+        }
+        let mut n = 0u32;
+        for (i, c) in chunk.iter().enumerate() {
+            let d = BASE64
+                .iter()
+                .position(|b| *b as char == *c)
+                .ok_or_else(|| format!("Invalid base64 char {}", c.escape_debug()))?;
+            n |= (d as u32) << (18 - 6 * i);
+        }
+        for i in 0..chunk.len() - 1 {
+            out.push((n >> (16 - 8 * i)) as u8);
+        }
+    }
+    Ok(out)
+}
@@ -1,4 +1,12 @@
+mod bytes;
 mod feed_impl;
+mod map;
+mod units;
 mod value;
 
+pub use bytes::{decode_base64, decode_hex, encode_base64, encode_hex};
+pub use map::Map;
+pub(crate) use units::{
+    duration_from_nanos, duration_unit, format_duration, format_size, size_unit,
+};
 pub use value::{BlockDecorator, BlockVec, Functor, FunctorInner, Token, Value, ValueRc};
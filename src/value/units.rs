@@ -0,0 +1,68 @@
+use std::time::Duration;
+
+// Units of duration literals, in nanoseconds, from the largest
+const DURATION_UNITS: [(&str, u128); 7] = [
+    ("d", 86_400_000_000_000),
+    ("h", 3_600_000_000_000),
+    ("m", 60_000_000_000),
+    ("s", 1_000_000_000),
+    ("ms", 1_000_000),
+    ("us", 1_000),
+    ("ns", 1),
+];
+
+// Units of size literals, in bytes, from the largest
+const SIZE_UNITS: [(&str, u64); 9] = [
+    ("TiB", 1 << 40),
+    ("TB", 1_000_000_000_000),
+    ("GiB", 1 << 30),
+    ("GB", 1_000_000_000),
+    ("MiB", 1 << 20),
+    ("MB", 1_000_000),
+    ("KiB", 1 << 10),
+    ("KB", 1_000),
+    ("B", 1),
+];
+
+pub(crate) fn duration_unit(unit: &str) -> Option<u128> {
+    DURATION_UNITS
+        .iter()
+        .find(|(name, _)| *name == unit)
+        .map(|(_, nanos)| *nanos)
+}
+
+pub(crate) fn size_unit(unit: &str) -> Option<u64> {
+    SIZE_UNITS
+        .iter()
+        .find(|(name, _)| *name == unit)
+        .map(|(_, bytes)| *bytes)
+}
+
+pub(crate) fn duration_from_nanos(nanos: u128) -> Option<Duration> {
+    let secs = u64::try_from(nanos / 1_000_000_000).ok()?;
+    Some(Duration::new(secs, (nanos % 1_000_000_000) as u32))
+}
+
+// Written in the largest unit that holds it exactly, so it reads back as the same value
+pub(crate) fn format_duration(duration: &Duration) -> String {
+    let nanos = duration.as_nanos();
+    if nanos == 0 {
+        return "0s".to_string();
+    }
+    let (unit, size) = DURATION_UNITS
+        .iter()
+        .find(|(_, size)| nanos.is_multiple_of(*size))
+        .unwrap();
+    format!("{}{}", nanos / size, unit)
+}
+
+pub(crate) fn format_size(bytes: u64) -> String {
+    if bytes == 0 {
+        return "0B".to_string();
+    }
+    let (unit, size) = SIZE_UNITS
+        .iter()
+        .find(|(_, size)| bytes.is_multiple_of(*size))
+        .unwrap();
+    format!("{}{}", bytes / size, unit)
+}
@@ -1,4 +1,5 @@
 use crate::prelude::*;
+use std::collections::HashSet;
 
 pub type ValueRc = RcCell<Value>;
 
@@ -21,13 +22,13 @@ impl Functor {
 
 impl PartialEq for Functor {
     fn eq(&self, other: &Self) -> bool {
-        return self as *const Self == other as *const Self;
+        std::ptr::eq(self, other)
     }
 }
 
 impl PartialOrd for Functor {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        return (self as *const Self).partial_cmp(&(other as *const Self));
+        (self as *const Self).partial_cmp(&(other as *const Self))
     }
 }
 
@@ -39,11 +40,18 @@ impl std::fmt::Debug for Functor {
 
 #[derive(Debug, Clone, PartialEq, PartialOrd)]
 pub enum Value {
-    Int(isize),
+    Int(i64),
     Float(f64),
     Bool(bool),
     Str(String),
+    Bytes(Vec<u8>),
+    Duration(std::time::Duration),
+    // A number of bytes
+    Size(u64),
     List(VecDeque<ValueRc>),
+    Map(Map),
+    // A scope held as a value, e.g. an item of a list of records
+    Scope(ScopeRc),
     Functor(Functor),
     Null,
 }
@@ -55,11 +63,78 @@ impl Value {
             Value::Float(f) => *f != 0.0,
             Value::Bool(b) => *b,
             Value::Str(s) => !s.is_empty(),
+            Value::Bytes(b) => !b.is_empty(),
+            Value::Duration(d) => !d.is_zero(),
+            Value::Size(s) => *s != 0,
             Value::List(l) => !l.is_empty(),
+            Value::Map(m) => !m.is_empty(),
+            Value::Scope(_) => true,
             Value::Functor(_) => true,
             Value::Null => false,
         }
     }
+
+    // The name of the kind of the value, as used in error messages
+    pub(crate) fn kind(&self) -> &'static str {
+        match self {
+            Value::Int(_) => "Int",
+            Value::Float(_) => "Float",
+            Value::Bool(_) => "Bool",
+            Value::Str(_) => "Str",
+            Value::Bytes(_) => "Bytes",
+            Value::Duration(_) => "Duration",
+            Value::Size(_) => "Size",
+            Value::List(_) => "List",
+            Value::Map(_) => "Map",
+            Value::Scope(_) => "Scope",
+            Value::Functor(_) => "Functor",
+            Value::Null => "Null",
+        }
+    }
+
+    // Whether the value reaches the target, a scope or value cell, through the lists, maps and
+    // scopes it holds, so storing it in the target would make the target own itself
+    pub(crate) fn holds(&self, target: *const ()) -> bool {
+        fn cell(value: &ValueRc, target: *const (), seen: &mut HashSet<*const ()>) -> bool {
+            let ptr = value.as_ptr() as *const ();
+            ptr == target
+                || (seen.insert(ptr) && value.try_borrow().is_ok_and(|v| walk(&v, target, seen)))
+        }
+        fn scope(rc: &ScopeRc, target: *const (), seen: &mut HashSet<*const ()>) -> bool {
+            let ptr = rc.as_ptr() as *const ();
+            // A scope borrowed mutably is being fed, and is checked as an ancestor instead
+            ptr == target
+                || (seen.insert(ptr)
+                    && rc.try_borrow().is_ok_and(|s| {
+                        s.get_values().values().any(|v| cell(v, target, seen))
+                            || s.get_scopes().values().any(|c| scope(c, target, seen))
+                    }))
+        }
+        fn walk(value: &Value, target: *const (), seen: &mut HashSet<*const ()>) -> bool {
+            match value {
+                Value::List(list) => list.iter().any(|item| cell(item, target, seen)),
+                Value::Map(map) => map.iter().any(|(_, item)| cell(item, target, seen)),
+                Value::Scope(s) => scope(s, target, seen),
+                _ => false,
+            }
+        }
+        walk(self, target, &mut HashSet::new())
+    }
+
+    pub fn as_duration(&self) -> Option<std::time::Duration> {
+        match self {
+            Value::Duration(d) => Some(*d),
+            _ => None,
+        }
+    }
+
+    // The number of bytes of a size
+    pub fn as_size(&self) -> Option<u64> {
+        match self {
+            Value::Size(s) => Some(*s),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -68,31 +143,63 @@ pub enum Token {
     Symbol(Vec<String>),
     Tag(Vec<String>),
     Block(BlockVec),
-    Operator(char),
+    Operator(String),
     Decorator(BlockDecorator),
 }
 
 #[derive(Debug, Clone)]
-pub struct BlockVec(Vec<Token>, BlockDecorator);
+pub struct BlockVec(Vec<Token>, BlockDecorator, Vec<Option<Span>>);
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
 pub enum BlockDecorator {
+    #[default]
     None,
     Functor,
     SubScope,
     IndepScope,
+    // A map literal, written in braces rather than parentheses
+    Map,
+    // A keyword listed in Syntax::decorators, handled by a hook registered on the scope
+    Custom(String),
+}
+
+impl BlockDecorator {
+    // The keyword used in source to write this decorator
+    pub fn keyword(&self) -> &str {
+        match self {
+            BlockDecorator::None => "",
+            BlockDecorator::Functor => "fn",
+            BlockDecorator::SubScope => "subsc",
+            BlockDecorator::IndepScope => "indep",
+            BlockDecorator::Map => "",
+            BlockDecorator::Custom(name) => name,
+        }
+    }
+}
+
+impl Default for BlockVec {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl BlockVec {
     pub fn new() -> Self {
-        Self(Vec::new(), BlockDecorator::None)
+        Self(Vec::new(), BlockDecorator::None, Vec::new())
     }
 
     pub fn push(&mut self, token: Token) {
         self.0.push(token);
+        self.2.push(None);
+    }
+
+    pub fn push_spanned(&mut self, token: Token, span: Span) {
+        self.0.push(token);
+        self.2.push(Some(span));
     }
 
     pub fn pop(&mut self) -> Option<Token> {
+        self.2.pop();
         self.0.pop()
     }
 
@@ -117,4 +224,14 @@ impl BlockVec {
     pub fn decor(&self) -> &BlockDecorator {
         &self.1
     }
+
+    #[inline(always)]
+    pub fn spans(&self) -> &Vec<Option<Span>> {
+        &self.2
+    }
+
+    // The span of the token at index, if it came from source
+    pub fn span(&self, index: usize) -> Option<Span> {
+        self.2.get(index).copied().flatten()
+    }
 }
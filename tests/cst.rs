@@ -0,0 +1,108 @@
+use ipml::*;
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    const EXAMPLES: [&str; 7] = [
+        "examples/example.ipml",
+        "examples/functor/external.ipml",
+        "examples/functor/functor.ipml",
+        "examples/import/main.ipml",
+        "examples/structure/copying.ipml",
+        "examples/structure/literal.ipml",
+        "examples/structure/scope.ipml",
+    ];
+
+    #[test]
+    fn test_lossless() {
+        for path in EXAMPLES {
+            let str = std::fs::read_to_string(path).unwrap();
+            let cst = Cst::parse(&str).unwrap();
+            assert_eq!(cst.to_string(), str, "{}", path);
+        }
+        let str = "  # leading\na=1# tail\n[b]:subsc(  c = \"x\\n\" )\n\n# end";
+        assert_eq!(Cst::parse(str).unwrap().to_string(), str);
+    }
+
+    // Describes a token tree without its spans
+    fn shape(token: &Token) -> String {
+        match token {
+            Token::Block(block) => format!(
+                "{:?}({})",
+                block.decor(),
+                block.data().iter().map(shape).collect::<Vec<_>>().join(" ")
+            ),
+            _ => format!("{:?}", token),
+        }
+    }
+
+    #[test]
+    fn test_format() {
+        for path in EXAMPLES {
+            let str = std::fs::read_to_string(path).unwrap();
+            let formatted = format_source(&str).unwrap();
+            assert_eq!(format_source(&formatted).unwrap(), formatted, "{}", path);
+            let before = Parser::new(str).parse().unwrap();
+            let after = Parser::new(formatted).parse().unwrap();
+            assert_eq!(shape(&before), shape(&after), "{}", path);
+        }
+
+        let str = "a=1 # x\nbbb ~ 2\n# lone\n\n\n\nccc = ( 1 (2 3) \"x\" )\n[s]:subsc( # open\n  z=1)\nf ! ( V = 1 )";
+        assert_eq!(
+            format_source(str).unwrap(),
+            concat!(
+                "a   = 1  # x\n",
+                "bbb ~ 2\n",
+                "# lone\n",
+                "\n",
+                "ccc = (1 (2 3) \"x\")\n",
+                "[s] : subsc (\n",
+                "    # open\n",
+                "    z = 1\n",
+                ")\n",
+                "f!(V = 1)\n",
+            )
+        );
+        assert_eq!(
+            format_source("m={a=1}\nn = { a = 1   b = {} }").unwrap(),
+            "m = {a = 1}\nn = {\n    a = 1\n    b = {}\n}\n"
+        );
+        let str = "m = { a = 1 # x\n}";
+        assert_eq!(Cst::parse(str).unwrap().to_string(), str);
+    }
+
+    #[test]
+    fn test_edit() {
+        let str = std::fs::read_to_string("examples/example.ipml").unwrap();
+        let mut cst = Cst::parse(&str).unwrap();
+        cst.set_value(&string_list!("my_scope", "bar"), "3.14")
+            .unwrap();
+        cst.set_value(&string_list!("my_scope", "condition"), "false")
+            .unwrap();
+        cst.set_value(&string_list!("baz"), "fn ( ret = 1 )")
+            .unwrap();
+        assert_eq!(
+            cst.to_string(),
+            str.replace("3.1415", "3.14").replace(
+                "condition = true",
+                "condition = false\nbaz = fn ( ret = 1 )"
+            )
+        );
+
+        let nodes = cst.find(&string_list!("my_scope", "bar")).unwrap();
+        assert!(matches!(
+            nodes[0].kind,
+            CstKind::Token {
+                token: Token::Value(Value::Float(_)),
+                ..
+            }
+        ));
+        let e = cst.set_value(&string_list!("foo"), "1 2").unwrap_err();
+        assert_eq!(e.to_string(), "[Cst] Expected a single value, but got 1 2");
+        assert!(e.span().is_none());
+        assert!(cst.set_value(&string_list!("foo"), "(1").is_err());
+        assert!(Cst::parse("a = (1 2))").is_err());
+    }
+}
@@ -0,0 +1,209 @@
+#![cfg(feature = "derive")]
+
+use ipml::*;
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    #[derive(Debug, PartialEq, FromScope, IntoScope)]
+    enum Mode {
+        Fast,
+        #[ipml(rename = "limited")]
+        Limited(u32),
+        Range(i64, i64),
+        Custom {
+            level: u8,
+        },
+    }
+
+    #[derive(Debug, PartialEq, FromScope, IntoScope)]
+    #[ipml(validate = "check_server")]
+    struct Server {
+        host: String,
+        #[ipml(validate = "check_port")]
+        port: u16,
+        tls: Option<bool>,
+    }
+
+    fn check_port(port: &u16) -> Result<(), String> {
+        match *port {
+            0 => Err("port must not be 0".to_string()),
+            _ => Ok(()),
+        }
+    }
+
+    fn check_server(server: &Server) -> Result<(), String> {
+        match server.host.as_str() {
+            "" => Err("host must not be empty".to_string()),
+            _ => Ok(()),
+        }
+    }
+
+    #[derive(Debug, PartialEq, Default, FromScope, IntoScope)]
+    struct Limits {
+        #[ipml(default = "default_timeout")]
+        timeout: Duration,
+        #[ipml(rename = "max-size", default)]
+        max_size: u64,
+    }
+
+    fn default_timeout() -> Duration {
+        Duration::from_secs(30)
+    }
+
+    #[derive(Debug, PartialEq, FromScope, IntoScope)]
+    struct Config {
+        name: String,
+        ratio: f64,
+        servers: Vec<Server>,
+        primary: Server,
+        labels: HashMap<String, i64>,
+        modes: Vec<Mode>,
+        key: Vec<u8>,
+        #[ipml(flatten)]
+        limits: Limits,
+        backup: Option<Server>,
+        r#type: String,
+    }
+
+    const SOURCE: &str = r#"
+        name = "web"
+        ratio = 1
+        servers = (
+            indep(host = "a" port = 80)
+            indep(host = "b" port = 81 tls = true)
+        )
+        [primary] : (host = "main" port = 443 tls = null)
+        labels = {tier = 1 zone = 2}
+        modes = ("Fast" {limited = 3} {Range = (1 2)} {Custom = {level = 2}})
+        key = hex"00ff"
+        `max-size` = 64KiB
+        type = "proxy"
+    "#;
+
+    fn scope_of(source: &str) -> Scope {
+        let mut scope = Scope::new();
+        scope.feed(&Parser::new(source).parse().unwrap()).unwrap();
+        scope
+    }
+
+    fn config_of(source: &str) -> Result<Config, RuntimeError> {
+        Config::from_scope(&mut scope_of(source))
+    }
+
+    #[test]
+    fn test_from_scope() {
+        let config = config_of(SOURCE).unwrap();
+        assert_eq!(config.name, "web");
+        assert_eq!(config.ratio, 1.0);
+        assert_eq!(
+            config.servers[1],
+            Server {
+                host: "b".to_string(),
+                port: 81,
+                tls: Some(true),
+            }
+        );
+        assert_eq!(config.primary.port, 443);
+        assert_eq!(config.primary.tls, None);
+        assert_eq!(config.labels["zone"], 2);
+        assert_eq!(
+            config.modes,
+            vec![
+                Mode::Fast,
+                Mode::Limited(3),
+                Mode::Range(1, 2),
+                Mode::Custom { level: 2 }
+            ]
+        );
+        assert_eq!(config.key, vec![0, 255]);
+        assert_eq!(
+            config.limits,
+            Limits {
+                timeout: Duration::from_secs(30),
+                max_size: 65536,
+            }
+        );
+        assert_eq!(config.backup, None);
+        assert_eq!(config.r#type, "proxy");
+
+        let mut scope = scope_of("[Custom] : (level = 7)");
+        assert_eq!(
+            Mode::from_scope(&mut scope).unwrap(),
+            Mode::Custom { level: 7 }
+        );
+        assert_eq!(Limits::from_scope(&mut Scope::new()).unwrap().max_size, 0);
+    }
+
+    #[test]
+    fn test_errors() {
+        let e = config_of(&SOURCE.replace("port = 443", "port = \"443\"")).unwrap_err();
+        assert_eq!(e.path(), ["primary", "port"]);
+        assert_eq!(
+            e.to_string(),
+            "[Convert] expected Int, got Str (at primary.port)"
+        );
+        let e = config_of(&SOURCE.replace("port = 81", "port = 70000")).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "[Convert] 70000 is out of range for u16 (at servers.1.port)"
+        );
+        let e = config_of(&SOURCE.replace("host = \"a\"", "")).unwrap_err();
+        assert_eq!(e.to_string(), "[Convert] missing field (at servers.0.host)");
+        let e = config_of(&SOURCE.replace("port = 80", "port = 0")).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "[Convert] port must not be 0 (at servers.0.port)"
+        );
+        let e = config_of(&SOURCE.replace("\"main\"", "\"\"")).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "[Convert] host must not be empty (at primary)"
+        );
+        let e = config_of(&SOURCE.replace("level = 2", "level = true")).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "[Convert] expected Int, got Bool (at modes.3.Custom.level)"
+        );
+        let e = config_of(&SOURCE.replace("(1 2)", "(1 \"2\")")).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "[Convert] expected Int, got Str (at modes.2.Range.1)"
+        );
+        let e = config_of(&SOURCE.replace("\"Fast\"", "\"Slow\"")).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "[Convert] unknown variant Slow, expected one of Fast, limited, Range, Custom (at modes.0)"
+        );
+        let e = Server::from_scope(&mut scope_of("host = \"\" port = 1")).unwrap_err();
+        assert_eq!(e.to_string(), "[Convert] host must not be empty");
+    }
+
+    #[test]
+    fn test_into_scope() {
+        let config = config_of(SOURCE).unwrap();
+        let scope = config.to_scope().unwrap();
+        let source = scope.borrow().to_ipml_string().unwrap();
+        assert!(source.contains("[primary] = (\n"), "{}", source);
+        assert!(source.contains("`max-size` = 65536\n"), "{}", source);
+        assert!(source.contains("timeout = 30s\n"), "{}", source);
+        let back = config_of(&source).unwrap_or_else(|e| panic!("{}\n{}", source, e));
+        assert_eq!(back, config);
+
+        let mut scope = Scope::new();
+        Mode::Fast.set_into(&mut scope).unwrap();
+        assert_eq!(Mode::from_scope(&mut scope).unwrap(), Mode::Fast);
+        assert_eq!(
+            Mode::Limited(4)
+                .to_value()
+                .unwrap()
+                .to_ipml_literal()
+                .unwrap(),
+            "{limited = 4}"
+        );
+    }
+}
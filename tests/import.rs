@@ -0,0 +1,126 @@
+use ipml::*;
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::path::Path;
+
+    // Counts the reads of the files it serves
+    struct Counting(MemoryResolver, Rc<Cell<usize>>);
+
+    impl Resolver for Counting {
+        fn read(&self, path: &Path) -> std::io::Result<String> {
+            self.1.set(self.1.get() + 1);
+            self.0.read(path)
+        }
+    }
+
+    fn scope(files: &[(&str, &str)]) -> (Scope, Rc<Cell<usize>>) {
+        let mut resolver = MemoryResolver::new();
+        for (path, source) in files {
+            resolver.insert(path, *source);
+        }
+        let reads = Rc::new(Cell::new(0));
+        let mut scope = Scope::new();
+        init_functor(&mut scope).unwrap();
+        scope.set_resolver(Counting(resolver, reads.clone()));
+        (scope, reads)
+    }
+
+    #[test]
+    fn test_import() {
+        let (mut scope, reads) = scope(&[
+            (
+                "conf/main.ipml",
+                "import!(P = \"base/common.ipml\")\n[db] : ()\nimport!(\n    P = \"./base/common.ipml\"\n    [T] ~ [db]\n)\ndb.port = 5432",
+            ),
+            ("conf/base/common.ipml", "import!(P = \"../shared.ipml\")\nport = 80"),
+            ("conf/shared.ipml", "host = \"localhost\""),
+        ]);
+        scope.import("conf/main.ipml").unwrap();
+
+        let value =
+            |scope: &mut Scope, path: &[String]| scope.query_value(path).unwrap().borrow().clone();
+        assert_eq!(value(&mut scope, &string_list!("port")), Value::Int(80));
+        assert_eq!(
+            value(&mut scope, &string_list!("db", "port")),
+            Value::Int(5432)
+        );
+        assert_eq!(
+            value(&mut scope, &string_list!("db", "host")),
+            Value::Str("localhost".to_string())
+        );
+        // Every file is read once, however often it is imported
+        assert_eq!(reads.get(), 3);
+        assert_eq!(
+            scope.registry().borrow().file_path(2),
+            Some(Path::new("conf/base/common.ipml"))
+        );
+    }
+
+    #[test]
+    fn test_import_syntax() {
+        let (mut scope, _) = scope(&[("host.ipml", "@port = 80\nlimit = lazy (@port 1)")]);
+        assert!(scope.import("host.ipml").is_err());
+
+        // Imported files are parsed with the syntax the host gives, also when imported before
+        let mut syntax = Syntax::default();
+        syntax.ident_start.push('@');
+        syntax.decorators.push("lazy".to_string());
+        scope.set_syntax(syntax);
+        // Blocks are taken as lists
+        struct Listed;
+        impl DecoratorHook for Listed {
+            fn as_value(
+                &self,
+                scope: &mut Scope,
+                block: &BlockVec,
+            ) -> Option<Result<ValueRc, RuntimeError>> {
+                Some(scope.to_list(block))
+            }
+        }
+        scope.register_decorator("lazy", Listed);
+        scope.import("host.ipml").unwrap();
+        let limit = scope.query_value(&string_list!("limit")).unwrap();
+        assert_eq!(limit.borrow().to_string(), "[80, 1]");
+    }
+
+    #[test]
+    fn test_import_errors() {
+        let (mut scope, reads) = scope(&[
+            ("cyc/a.ipml", "import!(P = \"b.ipml\")"),
+            ("cyc/b.ipml", "import!(P = \"a.ipml\")"),
+            ("bad.ipml", "x = (1"),
+            ("ok.ipml", "y = 1"),
+            ("missing.ipml", "import!(P = \"none.ipml\")"),
+        ]);
+        let e = scope.import("cyc/a.ipml").unwrap_err().to_string();
+        assert!(
+            e.contains("Cycle detected: cyc/a.ipml -> cyc/b.ipml -> cyc/a.ipml"),
+            "{}",
+            e
+        );
+        // The files being imported are popped, so later paths are not relative to them
+        scope.import("ok.ipml").unwrap();
+
+        // A file that fails to parse is neither registered nor cached
+        let files = |scope: &Scope| {
+            (1..)
+                .take_while(|&i| scope.registry().borrow().file_path(i).is_some())
+                .count()
+        };
+        let (count, read) = (files(&scope), reads.get());
+        let e = scope.import("bad.ipml").unwrap_err().to_string();
+        assert!(e.contains("In bad.ipml") && e.contains("Unclosed"), "{}", e);
+        assert_eq!(files(&scope), count);
+        assert!(scope.import("bad.ipml").is_err());
+        assert_eq!(reads.get(), read + 2);
+
+        let e = scope.import("missing.ipml").unwrap_err().to_string();
+        assert!(e.contains("Cannot read none.ipml"), "{}", e);
+        assert!(scope
+            .feed(&Parser::new("import!(P = 1)".to_string()).parse().unwrap())
+            .is_err());
+    }
+}
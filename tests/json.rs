@@ -0,0 +1,157 @@
+use ipml::*;
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn scope_of(source: &str) -> Scope {
+        let mut scope = Scope::new();
+        init_functor(&mut scope).unwrap();
+        scope.feed(&Parser::new(source).parse().unwrap()).unwrap();
+        scope
+    }
+
+    #[test]
+    fn test_to_json() {
+        let scope = scope_of(
+            r#"
+            name = "web\n\"1\""
+            ratio = 1.0
+            [server] : (port = 80 hosts = ("a" "b") empty = ())
+            labels = {zone = 2 tier = 1}
+            items = (indep(a = 1) {})
+            key = hex"00ff"
+            timeout = 1500ms
+            buffer = 64KiB
+            none = null
+            "#,
+        );
+        assert_eq!(
+            scope.to_json().unwrap(),
+            r#"{
+  "buffer": "64KiB",
+  "items": [
+    {
+      "a": 1
+    },
+    {}
+  ],
+  "key": "AP8=",
+  "labels": {
+    "zone": 2,
+    "tier": 1
+  },
+  "name": "web\n\"1\"",
+  "none": null,
+  "ratio": 1.0,
+  "server": {
+    "empty": [],
+    "hosts": [
+      "a",
+      "b"
+    ],
+    "port": 80
+  },
+  "timeout": "1500ms"
+}
+"#
+        );
+
+        assert!(scope_of("x = nan").to_json().is_err());
+        assert!(scope_of("x = -inf").to_json().is_err());
+        assert!(scope_of("x = (add)").to_json().is_err());
+        // A value and a scope of the same name would be written as duplicate keys
+        let e = scope_of("x = 1\n[x] : (y = 2)").to_json().unwrap_err();
+        assert!(
+            e.to_string().contains("x names both a value and a scope"),
+            "{}",
+            e
+        );
+        let e = scope_of("[s] : (x = 1\n[x] : ())").to_json().unwrap_err();
+        assert!(e.to_string().contains("x names both"), "{}", e);
+        assert_eq!(Scope::new().to_json().unwrap(), "{}\n");
+    }
+
+    #[test]
+    fn test_from_json() {
+        let scope = Scope::from_json(
+            r#"{
+                "name": "caf\u00e9 \ud83d\ude00\t",
+                "port": 8080,
+                "big": 99999999999999999999,
+                "ratio": -1.5e2,
+                "flags": [true, false, null],
+                "server": {"host": "a", "tls": {"on": true}},
+                "items": [{"a": 1}],
+                "empty key": {},
+                "dup": 1,
+                "dup": {"x": 2}
+            }"#,
+        )
+        .unwrap();
+        let mut scope = scope.borrow_mut();
+        let mut query = |path: &[&str]| {
+            let path: Vec<String> = path.iter().map(|s| s.to_string()).collect();
+            scope.query_value(&path).unwrap().borrow().clone()
+        };
+        assert_eq!(query(&["name"]), Value::Str("café 😀\t".to_string()));
+        assert_eq!(query(&["port"]), Value::Int(8080));
+        assert_eq!(query(&["big"]), Value::Float(1e20));
+        assert_eq!(query(&["ratio"]), Value::Float(-150.0));
+        assert_eq!(query(&["flags", "2"]), Value::Null);
+        assert_eq!(query(&["server", "tls", "on"]), Value::Bool(true));
+        assert_eq!(query(&["items", "0", "a"]), Value::Int(1));
+        assert_eq!(query(&["dup", "x"]), Value::Int(2));
+
+        // Back to JSON, then IPML and JSON again, gives the same JSON
+        let json = scope.to_json().unwrap();
+        let source = scope.to_ipml_string().unwrap();
+        assert_eq!(scope_of(&source).to_json().unwrap(), json);
+        assert_eq!(
+            Scope::from_json(&json).unwrap().borrow().to_json().unwrap(),
+            json
+        );
+    }
+
+    #[test]
+    fn test_json_errors() {
+        let error = |json: &str| Scope::from_json(json).unwrap_err().to_string();
+        assert_eq!(
+            error("[1]"),
+            "[Json] Expected {, but got [ at line 1, column 1"
+        );
+        assert_eq!(
+            error("{\n  \"a\": }"),
+            "[Json] Unexpected } at line 2, column 8"
+        );
+        assert_eq!(
+            error("{\"a\": 1} x"),
+            "[Json] Unexpected content after the object at line 1, column 10"
+        );
+        assert_eq!(
+            error("{\"a\": 01}"),
+            "[Json] Leading zeros are not allowed at line 1, column 9"
+        );
+        assert_eq!(
+            error("{\"a\": 1e400}"),
+            "[Json] Number 1e400 is out of range at line 1, column 12"
+        );
+        assert!(error("{\"a\": \"\\q\"}").starts_with("[Json] Invalid escape \\q"));
+        assert!(error("{\"a\": \"x").starts_with("[Json] Unterminated string"));
+        assert!(error("{\"a\": tru}").starts_with("[Json] Expected true"));
+        assert!(error("{\"a\": [1 2]}").starts_with("[Json] Expected , or ] after an item"));
+        assert!(error("{\"a\": \"\\ud800\"}").starts_with("[Json] Expected a low surrogate"));
+        assert_eq!(
+            error("{\"a\": {\"\": 1}}"),
+            "[Json] Empty keys are not supported at line 1, column 10"
+        );
+
+        // Nesting is limited rather than overflowing the stack
+        let nested =
+            |depth: usize| format!("{{\"a\": {}1{}}}", "[".repeat(depth), "]".repeat(depth));
+        assert!(Scope::from_json(&nested(127)).is_ok());
+        assert!(error(&nested(128)).starts_with("[Json] Nesting deeper than 128 levels"));
+        assert!(error(&"{\"a\": ".repeat(100_000)).starts_with("[Json] Nesting deeper"));
+    }
+}
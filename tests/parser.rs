@@ -0,0 +1,613 @@
+use ipml::*;
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn parse(str: &str) -> Token {
+        let mut parser = Parser::new(str.to_string());
+        match parser.parse() {
+            Ok(tokens) => tokens,
+            Err(e) => {
+                println!("{}", e);
+                panic!();
+            }
+        }
+    }
+
+    fn block(token: &Token) -> &BlockVec {
+        match token {
+            Token::Block(block) => block,
+            _ => panic!("Expected block, but got {}", token),
+        }
+    }
+
+    #[test]
+    fn test_multi_byte() {
+        let token = parse("a = \"héllo, 名前\" # ñ comment\nb = 2");
+        let block = block(&token);
+        assert_eq!(block.len(), 6);
+        match &block.data()[2] {
+            Token::Value(Value::Str(s)) => assert_eq!(s, "héllo, 名前"),
+            t => panic!("Expected string, but got {}", t),
+        }
+        match &block.data()[5] {
+            Token::Value(Value::Int(i)) => assert_eq!(*i, 2),
+            t => panic!("Expected int, but got {}", t),
+        }
+
+        // The old names of next_char and peek_char still work
+        #[allow(deprecated)]
+        {
+            let mut parser = Parser::new("名a");
+            assert_eq!(parser.peek(), Some('名'));
+            assert_eq!(parser.next(), Some('名'));
+            assert_eq!(parser.next_char(), Some('a'));
+        }
+    }
+
+    #[test]
+    fn test_spans() {
+        let token = parse("a = 1\n[b] : (\n    名 = \"x\"\n)");
+        let block = block(&token);
+        let span = block.span(3).unwrap();
+        assert_eq!((span.line, span.column), (2, 1));
+        let span = block.span(5).unwrap();
+        assert_eq!((span.start, span.end), (12, 29));
+        let inner = self::block(&block.data()[5]);
+        let span = inner.span(2).unwrap();
+        assert_eq!((span.line, span.column), (3, 9));
+
+        let mut scope = Scope::new();
+        let token = parse("a = 1\n[b] : (\n    c = 2\n    3 = d\n)");
+        let e = scope.feed(&token).unwrap_err();
+        let span = e.span().unwrap();
+        assert_eq!((span.line, span.column), (4, 5));
+        assert!(e.to_string().ends_with("(at line 4, column 5)"));
+    }
+
+    fn parse_err(str: &str) -> SyntaxError {
+        let mut parser = Parser::new(str.to_string());
+        match parser.parse() {
+            Ok(token) => panic!("Expected syntax error, but got {}", token),
+            Err(e) => e,
+        }
+    }
+
+    #[test]
+    fn test_unbalanced() {
+        let e = parse_err("a = 1\n[b] : (\n    c = (1 2\n)");
+        assert_eq!((e.line(), e.cursor()), (2, 7));
+
+        let e = parse_err("a = (1 2))\nb = 3");
+        assert_eq!((e.line(), e.cursor()), (1, 10));
+
+        let e = parse_err("f = fn\ng = 1");
+        assert_eq!((e.line(), e.cursor()), (1, 5));
+        assert!(e.to_string().contains("decorator fn"));
+
+        let e = parse_err("f = subsc");
+        assert_eq!((e.line(), e.cursor()), (1, 5));
+    }
+
+    fn values(str: &str) -> Vec<Value> {
+        let token = parse(str);
+        block(&token)
+            .data()
+            .iter()
+            .map(|token| match token {
+                Token::Value(value) => value.clone(),
+                _ => panic!("Expected value, but got {}", token),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_numbers() {
+        let v = values("0xFF 0o17 0b1010 -0x10 1_000_000 1e-9 2.5E3 -1.5 inf -inf");
+        assert_eq!(
+            v,
+            vec![
+                Value::Int(255),
+                Value::Int(15),
+                Value::Int(10),
+                Value::Int(-16),
+                Value::Int(1_000_000),
+                Value::Float(1e-9),
+                Value::Float(2500.0),
+                Value::Float(-1.5),
+                Value::Float(f64::INFINITY),
+                Value::Float(f64::NEG_INFINITY),
+            ]
+        );
+        match values("nan")[0] {
+            Value::Float(f) => assert!(f.is_nan()),
+            ref v => panic!("Expected float, but got {}", v),
+        }
+
+        let token = parse("a - b");
+        assert!(matches!(&block(&token).data()[1], Token::Operator(op) if op == "-"));
+
+        for str in [
+            "0xZZ",
+            "0b",
+            "1__0",
+            "1_",
+            "1._5",
+            "1.",
+            "1e",
+            "1e999",
+            "12ab",
+            "1.5.3",
+            "99999999999999999999",
+        ] {
+            let e = parse_err(&format!("a = {}", str));
+            assert_eq!((e.line(), e.cursor()), (1, 5), "{}", str);
+        }
+    }
+
+    #[test]
+    fn test_strings() {
+        let v = values(concat!(
+            r#""tab\t \"q\" \'s\' \0 \u{48}\u{1F600}" "#,
+            r##"r"C:\path\n" r#"a "quoted" \d+"# "##,
+            "\"\"\"\n    first\n      second \\u{21}\n    \"\"\" ",
+            "\"\"\"inline\"\"\""
+        ));
+        assert_eq!(
+            v,
+            vec![
+                Value::Str("tab\t \"q\" 's' \0 H\u{1F600}".to_string()),
+                Value::Str("C:\\path\\n".to_string()),
+                Value::Str("a \"quoted\" \\d+".to_string()),
+                Value::Str("first\n  second !\n".to_string()),
+                Value::Str("inline".to_string()),
+            ]
+        );
+
+        let e = parse_err("a = 1\nb = \"abc\nc = 2");
+        assert_eq!((e.line(), e.cursor()), (2, 5));
+        let e = parse_err("a = \"ab\\qc\"");
+        assert_eq!((e.line(), e.cursor()), (1, 8));
+        let e = parse_err("a = \"\\u{110000}\"");
+        assert_eq!((e.line(), e.cursor()), (1, 6));
+        let e = parse_err("a = r#\"abc\"");
+        assert_eq!((e.line(), e.cursor()), (1, 5));
+        let e = parse_err("a = \"\"\"\n  \\x\n\"\"\"");
+        assert_eq!((e.line(), e.cursor()), (2, 3));
+    }
+
+    #[test]
+    fn test_recover() {
+        let str = "a = 0xZZ\nb = \"ok\"\nc = \"abc\n[d] : (\n    e = 1.5.3\n    f = (1 2)\n)) g";
+        let mut parser = Parser::new(str.to_string());
+        let (token, errors) = parser.parse_recover();
+        let positions: Vec<_> = errors.iter().map(|e| (e.line(), e.cursor())).collect();
+        assert_eq!(positions, vec![(1, 5), (3, 5), (5, 9), (7, 2)]);
+
+        // Broken statements are left out whole, so the ones after them keep their place
+        let block = block(&token);
+        assert_eq!(block.len(), 7);
+        assert!(matches!(&block.data()[0], Token::Symbol(name) if name[0] == "b"));
+        assert!(matches!(&block.data()[2], Token::Value(Value::Str(s)) if s == "ok"));
+        let inner = self::block(&block.data()[5]);
+        assert_eq!(inner.len(), 3);
+        assert!(matches!(&inner.data()[0], Token::Symbol(name) if name[0] == "f"));
+
+        let (token, errors) = Parser::new("a = 1\nb = 0xZZ\nc = 2".to_string()).parse_recover();
+        assert_eq!(errors.len(), 1);
+        let mut scope = Scope::new();
+        scope.feed(&token).unwrap();
+        let c = scope.query_value(&string_list!("c")).unwrap();
+        assert_eq!(*c.borrow(), Value::Int(2));
+        assert_eq!(self::block(&token).len(), 6);
+        assert!(!scope.to_string().contains("b:"), "{}", scope);
+
+        let e = parse_err(str);
+        assert_eq!((e.line(), e.cursor()), (1, 5));
+
+        // A tokenizer that only implements tokenize still builds, and one stuck on an error
+        // stops rather than reporting it forever
+        struct Stuck(usize);
+        impl Tokenize for Stuck {
+            fn tokenize(&mut self) -> Option<Result<(Token, Span), SyntaxError>> {
+                self.0 += 1;
+                match self.0 {
+                    1..=3 => Some(Ok((Token::Value(Value::Int(1)), Span::default()))),
+                    _ => Some(Err(SyntaxError::new(1, 2, "Stuck".to_string()))),
+                }
+            }
+        }
+        let (token, errors) = Stuck(0).parse_recover();
+        assert_eq!(self::block(&token).len(), 3);
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn test_stream() {
+        let mut str = std::fs::read_to_string("examples/functor/external.ipml").unwrap();
+        str.push_str("text = \"\"\"\n    multi\n    line\n    \"\"\"\nraw = r#\"a\nb\"#\n# end");
+        str.push_str("\nesc = # c\n\"a\\\"\nb\\\\\"");
+        let expected = parse(&str);
+
+        // A tiny buffer makes sure tokens are cut across reads
+        let reader = std::io::BufReader::with_capacity(3, str.as_bytes());
+        let mut parser = StreamParser::new(reader);
+        let token = parser.parse().unwrap();
+        assert_eq!(format!("{:?}", token), format!("{:?}", expected));
+
+        // A long text block is not tokenized again after every line it is read
+        let str = format!(
+            "text = \"\"\"\n{}\"\"\"",
+            "some line of text\n".repeat(50_000)
+        );
+        let start = std::time::Instant::now();
+        let token = StreamParser::new(str.as_bytes()).parse().unwrap();
+        let elapsed = start.elapsed();
+        assert_eq!(block(&token).len(), 3);
+        assert!(elapsed.as_secs() < 10, "Streaming took {:?}", elapsed);
+    }
+
+    #[test]
+    fn test_feed_stream() {
+        struct Broken;
+        impl std::io::Read for Broken {
+            fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::other("connection lost"))
+            }
+        }
+
+        // Triples are fed as they arrive, before the stream fails
+        let reader = std::io::BufReader::new(std::io::Read::chain(
+            "a = 1\n[b] : (\n    c = \"x\"\n)\n".as_bytes(),
+            Broken,
+        ));
+        let mut parser = StreamParser::new(reader);
+        let mut scope = Scope::new();
+        let e = scope.feed_stream(&mut parser).unwrap_err();
+        assert!(e.to_string().contains("connection lost"));
+        assert!(!e.to_string().contains("line"), "{}", e);
+        let c = scope.query_value(&string_list!("b", "c")).unwrap();
+        assert_eq!(*c.borrow(), Value::Str("x".to_string()));
+
+        // Failing to read is an IO error, which has no place in the source
+        let mut parser = StreamParser::new(std::io::BufReader::new(Broken));
+        let e = parser.parse().unwrap_err();
+        assert!(matches!(&e, SyntaxError::Io(e) if e.to_string() == "connection lost"));
+        assert!(parser.tokenize().is_none());
+    }
+
+    #[test]
+    fn test_feed_stream_temp() {
+        // Temporaries live as long as when the whole input is fed at once, and are dropped
+        // once it ends
+        let source = "ret = 2\nv = ret\n[x] : (y = 1)\nx.y ~ ret";
+        let mut fed = Scope::new();
+        fed.mode.filter = true;
+        fed.feed(&parse(source)).unwrap();
+        let mut streamed = Scope::new();
+        streamed.mode.filter = true;
+        streamed
+            .feed_stream(&mut StreamParser::new(source.as_bytes()))
+            .unwrap();
+        for scope in [&mut fed, &mut streamed] {
+            let v = scope.query_value(&string_list!("v")).unwrap();
+            assert_eq!(*v.borrow(), Value::Int(2));
+            let y = scope.query_value(&string_list!("x", "y")).unwrap();
+            assert_eq!(*y.borrow(), Value::Int(2));
+            assert!(!scope.to_string().contains(RETURN), "{}", scope);
+        }
+    }
+
+    #[test]
+    fn test_idents() {
+        let token = parse("größe = 1\n名前.`content-type`.x0 = 2\n[`a.b`.c] : ()\n`true` = 3");
+        let block = block(&token);
+        let names: Vec<_> = [0, 3, 6, 9]
+            .iter()
+            .map(|i| match &block.data()[*i] {
+                Token::Symbol(name) | Token::Tag(name) => name.clone(),
+                t => panic!("Expected name, but got {}", t),
+            })
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                string_list!("größe").to_vec(),
+                string_list!("名前", "content-type", "x0").to_vec(),
+                string_list!("a.b", "c").to_vec(),
+                string_list!("true").to_vec(),
+            ]
+        );
+
+        let syntax = Syntax::default();
+        for name in names
+            .iter()
+            .chain([string_list!("0", "1 2", "a`b\\").to_vec()].iter())
+        {
+            let str = format!("{} = 1", syntax.quote_path(name));
+            match &self::block(&parse(&str)).data()[0] {
+                Token::Symbol(parsed) => assert_eq!(parsed, name),
+                t => panic!("Expected symbol, but got {}", t),
+            }
+        }
+        assert_eq!(
+            syntax.quote_path(&string_list!("a", "0", "b c")),
+            "a.0.`b c`"
+        );
+
+        let mut parser = Parser::new("@x = größe".to_string());
+        parser.set_syntax(Syntax {
+            unicode_idents: false,
+            ident_start: vec!['@'],
+            ..Default::default()
+        });
+        let token = parser.parse().unwrap();
+        let block = self::block(&token);
+        assert!(matches!(&block.data()[0], Token::Symbol(name) if name[0] == "@x"));
+        assert!(matches!(&block.data()[2], Token::Symbol(name) if name[0] == "gr"));
+        assert!(matches!(&block.data()[3], Token::Operator(op) if op == "ö"));
+
+        let e = parse_err("a = `abc");
+        assert_eq!((e.line(), e.cursor()), (1, 5));
+        let e = parse_err("[abc");
+        assert_eq!((e.line(), e.cursor()), (1, 1));
+    }
+
+    #[test]
+    fn test_large_input() {
+        // Roughly 2 MB of source, which took minutes with the old quadratic cursor
+        let mut str = String::new();
+        let mut count = 0;
+        while str.len() < 2 * 1024 * 1024 {
+            str.push_str(&format!(
+                "[s{0}] : (\n    name = \"entry {0} ✓\"  # comment\n    values = (1 2.5 true null)\n)\n",
+                count
+            ));
+            count += 1;
+        }
+
+        let start = std::time::Instant::now();
+        let token = parse(&str);
+        let elapsed = start.elapsed();
+
+        assert_eq!(block(&token).len(), count * 3);
+        assert!(
+            elapsed.as_secs() < 20,
+            "Parsing {} bytes took {:?}",
+            str.len(),
+            elapsed
+        );
+
+        // Streaming drops consumed input along the way, spans must stay the same
+        let mut parser = StreamParser::new(str.as_bytes());
+        let streamed = parser.parse().unwrap();
+        let (block, streamed) = (block(&token), self::block(&streamed));
+        assert_eq!(streamed.len(), block.len());
+        assert_eq!(streamed.spans(), block.spans());
+    }
+
+    #[test]
+    fn test_operators() {
+        let token = parse("a->b c<-d e ?= 1 f += 2");
+        let ops: Vec<_> = block(&token)
+            .data()
+            .iter()
+            .skip(1)
+            .step_by(3)
+            .map(|token| match token {
+                Token::Operator(op) => op.as_str(),
+                _ => panic!("Expected operator, but got {:?}", token),
+            })
+            .collect();
+        assert_eq!(ops, ["->", "<-", "?=", "+="]);
+
+        let mut scope = Scope::new();
+        scope.register_operator("+=", |scope, lhs, rhs| {
+            let rhs = scope.as_true_value(rhs)?;
+            let lhs = scope.as_linked_value(lhs)?;
+            let sum = match (&*lhs.borrow(), &*rhs.borrow()) {
+                (Value::Int(a), Value::Int(b)) => Value::Int(a + b),
+                _ => return Err(RuntimeError::new("[Add] Expected ints".to_string())),
+            };
+            *lhs.borrow_mut() = sum;
+            Ok(rc_cell(Value::Null))
+        });
+        scope
+            .feed(&parse(
+                "a = 1\na += 2\n[s] : (\n    b = 10\n    b += a\n)\nc ?= 5\nc ?= 6\na ?= 0",
+            ))
+            .unwrap();
+        let mut value = |path: &[String]| scope.query_value(path).unwrap().borrow().clone();
+        assert_eq!(value(&string_list!("a")), Value::Int(3));
+        assert_eq!(value(&string_list!("s", "b")), Value::Int(13));
+        assert_eq!(value(&string_list!("c")), Value::Int(5));
+        assert!(scope.feed(&parse("a -> 1")).is_err());
+
+        // A linked scope keeps its own operators, and one with none takes those of its parent
+        let child = rc_cell(Scope::new());
+        child
+            .borrow_mut()
+            .register_operator("->", |_, _, _| Ok(rc_cell(Value::Int(1))));
+        scope.link_child("child", child.clone()).unwrap();
+        assert!(child
+            .borrow()
+            .registry()
+            .borrow()
+            .get_operator("->")
+            .is_some());
+        assert!(child
+            .borrow()
+            .registry()
+            .borrow()
+            .get_operator("+=")
+            .is_none());
+        let grandchild = rc_cell(Scope::new());
+        let empty = rc_cell(Scope::new());
+        empty
+            .borrow_mut()
+            .link_child("g", grandchild.clone())
+            .unwrap();
+        scope.link_child("empty", empty.clone()).unwrap();
+        assert!(Rc::ptr_eq(empty.borrow().registry(), scope.registry()));
+        assert!(Rc::ptr_eq(grandchild.borrow().registry(), scope.registry()));
+    }
+
+    #[test]
+    fn test_decorators() {
+        // Lazy blocks are evaluated as lists only once called
+        struct Lazy;
+        impl DecoratorHook for Lazy {
+            fn as_value(
+                &self,
+                _scope: &mut Scope,
+                block: &BlockVec,
+            ) -> Option<Result<ValueRc, RuntimeError>> {
+                let block = block.clone();
+                Some(Ok(rc_cell(Value::Functor(Functor::new(move |scope| {
+                    let list = scope.to_list(&block)?;
+                    scope.set_value(&[RETURN.to_string()], list.clone())?;
+                    Ok(list)
+                })))))
+            }
+        }
+        // Template scopes start with a default name
+        struct Template;
+        impl DecoratorHook for Template {
+            fn to_scope(
+                &self,
+                scope: &mut Scope,
+                block: &BlockVec,
+            ) -> Option<Result<ScopeRc, RuntimeError>> {
+                let mut block = block.clone();
+                block.set_decor(BlockDecorator::None);
+                let result = scope.to_scope(&Token::Block(block));
+                Some(result.and_then(|result| {
+                    result
+                        .borrow_mut()
+                        .feed(&Parser::new("name ?= \"unnamed\"".to_string()).parse()?)?;
+                    Ok(result)
+                }))
+            }
+        }
+
+        let source = "x = 1\nl = lazy (x 2)\nx = 5\nl!()\nv ~ ret\n[t] = template (size = 3)";
+        let mut parser = Parser::new(source.to_string());
+        let mut syntax = Syntax::default();
+        syntax
+            .decorators
+            .extend(["lazy".to_string(), "template".to_string()]);
+        parser.set_syntax(syntax);
+        let token = parser.parse().unwrap();
+        assert!(matches!(
+            &block(&token).data()[5],
+            Token::Block(block) if *block.decor() == BlockDecorator::Custom("lazy".to_string())
+        ));
+        assert_eq!(parser.syntax().quote_path(&string_list!("lazy")), "`lazy`");
+
+        let mut scope = Scope::new();
+        assert!(scope.feed(&token).is_err());
+        scope.register_decorator("lazy", Lazy);
+        scope.register_decorator("template", Template);
+        scope.feed(&token).unwrap();
+        let v = scope.query_value(&string_list!("v")).unwrap();
+        assert_eq!(v.borrow().to_string(), "[5, 2]");
+        let name = scope.query_value(&string_list!("t", "name")).unwrap();
+        assert_eq!(*name.borrow(), Value::Str("unnamed".to_string()));
+
+        // Without a keyword listed, the name is a plain symbol
+        let token = parse("lazy = 1");
+        assert!(matches!(&block(&token).data()[0], Token::Symbol(_)));
+    }
+
+    #[test]
+    fn test_cache() {
+        let syntax = Syntax::default();
+        let source = std::fs::read_to_string("examples/example.ipml").unwrap()
+            + "\nx = (-12 1e300 nan \"✓\\n\" null)\ny = fn (ret = 1)\nz = {k = {}}";
+        let token = Parser::with_file(source.clone(), 2).parse().unwrap();
+        let bytes = write_cache(&token, &source, &syntax).unwrap();
+        let cached = read_cache(&bytes, &source, &syntax, 2).unwrap();
+        assert_eq!(format!("{:?}", cached), format!("{:?}", token));
+
+        // Stale, truncated or foreign caches are all rejected
+        assert!(read_cache(&bytes, &(source.clone() + " "), &syntax, 2).is_none());
+        assert!(read_cache(&bytes[..bytes.len() - 1], &source, &syntax, 2).is_none());
+        assert!(read_cache(b"not a cache", &source, &syntax, 2).is_none());
+        let other = Syntax {
+            unicode_idents: false,
+            ..Default::default()
+        };
+        assert!(read_cache(&bytes, &source, &other, 2).is_none());
+        let mut newer = bytes.clone();
+        newer[4] += 1;
+        assert!(read_cache(&newer, &source, &syntax, 2).is_none());
+
+        let path = std::env::temp_dir().join(format!("ipml-cache-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let parsed = parse_cached(source.clone(), 0, &syntax, &path).unwrap();
+        assert_eq!(
+            std::fs::read(&path).unwrap(),
+            write_cache(&parsed, &source, &syntax).unwrap()
+        );
+        let loaded = parse_cached(source.clone(), 0, &syntax, &path).unwrap();
+        assert_eq!(format!("{:?}", loaded), format!("{:?}", parsed));
+        // A changed source is parsed again, and the cache rewritten
+        let changed = parse_cached("a = 1".to_string(), 0, &syntax, &path).unwrap();
+        assert_eq!(block(&changed).len(), 3);
+        assert!(read_cache(&std::fs::read(&path).unwrap(), "a = 1", &syntax, 0).is_some());
+        // As is one parsed with another syntax
+        let mut syntax = Syntax::default();
+        syntax.ident_start.push('@');
+        let plain = parse_cached("@a = 1".to_string(), 0, &Syntax::default(), &path).unwrap();
+        assert!(matches!(&block(&plain).data()[0], Token::Operator(op) if op == "@"));
+        let changed = parse_cached("@a = 1".to_string(), 0, &syntax, &path).unwrap();
+        assert!(matches!(&block(&changed).data()[0], Token::Symbol(name) if name[0] == "@a"));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_lexer() {
+        let mut sources: Vec<String> = ["examples/example.ipml", "examples/structure/scope.ipml"]
+            .iter()
+            .map(|path| std::fs::read_to_string(path).unwrap())
+            .collect();
+        sources.push(
+            "a.`b\\`c`.d = \"x\\ty\"\n[`t.u`.v] : subsc (s = r#\"raw\"# n = -1.5e3)\nf ! (q ?= \"\"\"\n  text\n  \"\"\")".to_string(),
+        );
+        for source in &sources {
+            let borrowed = Lexer::new(source).parse().unwrap().to_token();
+            let owned = Parser::new(source.as_str()).parse().unwrap();
+            assert_eq!(format!("{:?}", borrowed), format!("{:?}", owned));
+        }
+
+        // Names and strings without escapes point into the source
+        let source = &sources[2];
+        let inside = |s: &str| source.as_bytes().as_ptr_range().contains(&s.as_ptr());
+        let token = Lexer::new(source).parse().unwrap();
+        let TokenRef::Block(block) = token else {
+            panic!("Expected block");
+        };
+        let TokenRef::Symbol(path) = &block.data()[0] else {
+            panic!("Expected symbol");
+        };
+        let sections: Vec<_> = path.sections().collect();
+        assert_eq!(sections, ["a", "b`c", "d"]);
+        assert!(inside(&sections[0]) && !inside(&sections[1]) && inside(&sections[2]));
+        assert!(matches!(&block.data()[2], TokenRef::Str(str) if str == "x\ty" && !inside(str)));
+        let TokenRef::Tag(tag) = &block.data()[3] else {
+            panic!("Expected tag");
+        };
+        assert_eq!(tag.raw(), "`t.u`.v");
+        assert_eq!(tag.to_vec(), ["t.u", "v"]);
+        let TokenRef::Block(inner) = &block.data()[5] else {
+            panic!("Expected block");
+        };
+        assert_eq!(*inner.decor(), BlockDecorator::SubScope);
+        assert!(matches!(&inner.data()[2], TokenRef::Str(str) if str == "raw" && inside(str)));
+        assert!(matches!(&block.data()[7], TokenRef::Operator(op) if *op == "!" && inside(op)));
+
+        assert!(Lexer::new("a = (1").parse().is_err());
+    }
+}
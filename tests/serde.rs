@@ -0,0 +1,153 @@
+#![cfg(feature = "serde")]
+
+use ipml::*;
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use serde::{Deserialize, Serialize};
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Mode {
+        Fast,
+        Limited(u32),
+        Custom { level: u8 },
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Server {
+        host: String,
+        port: u16,
+        tls: Option<bool>,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Config {
+        name: String,
+        ratio: f64,
+        servers: Vec<Server>,
+        primary: Server,
+        labels: HashMap<String, i64>,
+        modes: Vec<Mode>,
+        #[serde(with = "serde_bytes_helper")]
+        key: Vec<u8>,
+        timeout: Duration,
+        buffer: u64,
+        backup: Option<Server>,
+    }
+
+    // Vec<u8> is a list of ints to serde unless told otherwise
+    mod serde_bytes_helper {
+        use serde::{Deserializer, Serializer};
+
+        pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_bytes(bytes)
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Vec<u8>, D::Error> {
+            struct Visitor;
+            impl serde::de::Visitor<'_> for Visitor {
+                type Value = Vec<u8>;
+                fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                    write!(f, "bytes")
+                }
+                fn visit_bytes<E>(self, v: &[u8]) -> Result<Vec<u8>, E> {
+                    Ok(v.to_vec())
+                }
+            }
+            deserializer.deserialize_bytes(Visitor)
+        }
+    }
+
+    const SOURCE: &str = r#"
+        name = "web"
+        ratio = 1
+        servers = (
+            indep(host = "a" port = 80)
+            indep(host = "b" port = 81 tls = true)
+        )
+        [primary] : (host = "main" port = 443 tls = null)
+        labels = {tier = 1 zone = 2}
+        modes = ("Fast" {Limited = 3} {Custom = {level = 2}})
+        key = hex"00ff"
+        timeout = 1500ms
+        buffer = 64KiB
+    "#;
+
+    #[test]
+    fn test_from_str() {
+        let config: Config = from_str(SOURCE).unwrap();
+        assert_eq!(config.name, "web");
+        assert_eq!(config.ratio, 1.0);
+        assert_eq!(
+            config.servers[1],
+            Server {
+                host: "b".to_string(),
+                port: 81,
+                tls: Some(true),
+            }
+        );
+        assert_eq!(config.primary.port, 443);
+        assert_eq!(config.primary.tls, None);
+        assert_eq!(config.labels["zone"], 2);
+        assert_eq!(
+            config.modes,
+            vec![Mode::Fast, Mode::Limited(3), Mode::Custom { level: 2 }]
+        );
+        assert_eq!(config.key, vec![0, 255]);
+        assert_eq!(config.timeout, Duration::from_millis(1500));
+        assert_eq!(config.buffer, 65536);
+        assert_eq!(config.backup, None);
+
+        let mut scope = Scope::new();
+        scope
+            .feed(&Parser::new("host = \"x\" port = 1").parse().unwrap())
+            .unwrap();
+        let server: Server = from_scope(&scope).unwrap();
+        assert_eq!(server.host, "x");
+    }
+
+    #[test]
+    fn test_errors() {
+        let e = from_str::<Config>(&SOURCE.replace("port = 443", "port = \"443\"")).unwrap_err();
+        assert_eq!(e.path(), ["primary", "port"]);
+        assert_eq!(
+            e.to_string(),
+            "[Serde] expected Int, got Str (at primary.port)"
+        );
+        let e = from_str::<Config>(&SOURCE.replace("port = 81", "port = 70000"))
+            .unwrap_err()
+            .to_string();
+        assert!(
+            e.starts_with("[Serde] invalid value") && e.ends_with("(at servers.1.port)"),
+            "{}",
+            e
+        );
+        let e = from_str::<Config>(&SOURCE.replace("host = \"a\"", ""))
+            .unwrap_err()
+            .to_string();
+        assert_eq!(e, "[Serde] missing field `host` (at servers.0)");
+        let e = from_str::<Server>("host = 1").unwrap_err().to_string();
+        assert_eq!(e, "[Serde] expected Str, got Int (at host)");
+        assert!(from_str::<Server>("host = (").is_err());
+    }
+
+    #[test]
+    fn test_to_string() {
+        let config: Config = from_str(SOURCE).unwrap();
+        let source = to_string(&config).unwrap();
+        assert!(source.contains("[primary] = (\n"), "{}", source);
+        assert!(source.contains("= hex\"00ff\"\n"), "{}", source);
+        let back: Config = from_str(&source).unwrap_or_else(|e| panic!("{}\n{}", source, e));
+        assert_eq!(back, config);
+
+        assert!(to_string(&1).is_err());
+        assert!(to_string(&HashMap::from([((1, 2), 3)])).is_err());
+        assert_eq!(to_string(&HashMap::from([("a", 1)])).unwrap(), "a = 1\n");
+    }
+}
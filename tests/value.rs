@@ -0,0 +1,511 @@
+use ipml::*;
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    fn run(source: &str) -> Result<Scope, RuntimeError> {
+        let mut scope = Scope::new();
+        init_functor(&mut scope)?;
+        scope.feed(&Parser::new(source).parse()?)?;
+        Ok(scope)
+    }
+
+    fn value(scope: &mut Scope, path: &[String]) -> Value {
+        scope.query_value(path).unwrap().borrow().clone()
+    }
+
+    #[test]
+    fn test_map() {
+        let mut scope = run(r#"
+            x = 2
+            m = {b = 1 "content-type" = "text" a = (x {}) b = 3}
+            get!(M ~ m K = "a")
+            a ~ ret
+            set!(M ~ m K = "c" V = {d = true})
+            remove!(M ~ m K = "content-type")
+            has!(M ~ m K = "content-type")
+            gone ~ ret
+            keys!(M ~ m)
+            keys ~ ret
+        "#)
+        .unwrap();
+        let m = value(&mut scope, &string_list!("m"));
+        // Keys keep the order they were first written in
+        assert_eq!(m.to_string(), "{b: 3, a: [2, {}], c: {d: true}}");
+        assert_eq!(value(&mut scope, &string_list!("a")).to_string(), "[2, {}]");
+        assert_eq!(value(&mut scope, &string_list!("gone")), Value::Bool(false));
+        assert_eq!(
+            value(&mut scope, &string_list!("keys")).to_string(),
+            "[b, a, c]"
+        );
+
+        // Maps are equal whatever the order of their keys
+        let other = run("m = {c = {d = true} a = (2 {}) b = 3}").unwrap();
+        assert_eq!(value(&mut other.clone(), &string_list!("m")), m);
+
+        let e = run("m = {a.b = 1}").unwrap_err().to_string();
+        assert!(e.contains("Expected a name or string as key"), "{}", e);
+        assert!(run("m = {a : 1}").is_err());
+        assert!(run("m = {a = 1 b}").is_err());
+
+        // The map may be passed as the key, but never set as a value of itself
+        let e = run("m = {}\nset!(M ~ m K ~ m V = 1)")
+            .unwrap_err()
+            .to_string();
+        assert!(e.contains("Expected a map, a string and a value"), "{}", e);
+        let e = run("m = {}\nremove!(M ~ m K ~ m)").unwrap_err().to_string();
+        assert!(e.contains("Expected a map and a string"), "{}", e);
+        let e = run("m = {}\nset!(M ~ m K = \"x\" V ~ m)")
+            .unwrap_err()
+            .to_string();
+        assert!(e.contains("Cannot set a map as a value of itself"), "{}", e);
+        for source in [
+            "m = {}\nset!(M ~ m K = \"x\" V = {y ~ m})",
+            "m = {}\nl = (1)\nl.0 ~ m\nset!(M ~ m K = \"x\" V ~ l)",
+            "m = {}\n[a] : (n ~ m)\nset!(M ~ m K = \"x\" V = [a])",
+        ] {
+            let e = run(source).unwrap_err().to_string();
+            assert!(
+                e.contains("Cannot set a map as a value of itself"),
+                "{}: {}",
+                source,
+                e
+            );
+        }
+        assert!(Parser::new("m = {a = 1)").parse().is_err());
+        assert!(Parser::new("m = (a = 1}").parse().is_err());
+        assert!(Parser::new("m = fn {a = 1}").parse().is_err());
+    }
+
+    #[test]
+    fn test_scope_value() {
+        let mut scope = run(r#"
+            port = 80
+            [cfg] : (name = "c" port = 3)
+            servers = (subsc(name = "a" port = port) indep(name = "b") [cfg])
+            seen = servers.0.port
+            servers.1.port = 2
+            servers.0.port = 81
+            first ~ servers.0.port
+            m = {list = (1 2) inner = {x = 1}}
+            m.list.1 = 3
+            m.inner.y = m.list.0
+        "#)
+        .unwrap();
+        // Sub-scopes see outer names while evaluated
+        assert_eq!(value(&mut scope, &string_list!("seen")), Value::Int(80));
+        assert_eq!(value(&mut scope, &string_list!("first")), Value::Int(81));
+        assert_eq!(
+            value(&mut scope, &string_list!("servers", "1", "port")),
+            Value::Int(2)
+        );
+        // Tags are shared with the scope they name
+        scope
+            .feed(&Parser::new("servers.2.port = 4").parse().unwrap())
+            .unwrap();
+        assert_eq!(
+            value(&mut scope, &string_list!("cfg", "port")),
+            Value::Int(4)
+        );
+        assert_eq!(
+            value(&mut scope, &string_list!("m")).to_string(),
+            "{list: [1, 3], inner: {x: 1, y: 1}}"
+        );
+        // Anonymous scopes are not left behind in the scope they were written in
+        assert_eq!(
+            scope
+                .query_scope(&string_list!("[anonymous]"))
+                .unwrap()
+                .borrow()
+                .to_string(),
+            "Scope {}"
+        );
+
+        let other = run("s = (indep(port = 81 name = \"a\"))").unwrap();
+        assert_eq!(
+            value(&mut other.clone(), &string_list!("s", "0")),
+            value(&mut scope, &string_list!("servers", "0"))
+        );
+
+        let e = run("l = (1 2) x = l.2").unwrap_err().to_string();
+        assert!(e.contains("index below 2"), "{}", e);
+        let e = run("m = {a = 1} x = m.b").unwrap_err().to_string();
+        assert!(e.contains("No key b in a map of 1 entries"), "{}", e);
+        assert!(run("l = (1 2) l.x = 1").is_err());
+        assert!(run("l = (1 2) l.0.x = 1").is_err());
+
+        // Values and sub-scopes are apart, a path goes through a sub-scope of its name first
+        let mut scope = run("a = (1 2)\n[a] : (b = 5)\nx = a.b\na.c = 6").unwrap();
+        assert_eq!(value(&mut scope, &string_list!("x")), Value::Int(5));
+        assert_eq!(value(&mut scope, &string_list!("a", "c")), Value::Int(6));
+        assert_eq!(value(&mut scope, &string_list!("a")).to_string(), "[1, 2]");
+        let mut scope = run("a = (1 2)\n[s] : ([a] : (b = 5) x = a.b)").unwrap();
+        assert_eq!(value(&mut scope, &string_list!("s", "x")), Value::Int(5));
+
+        // A scope kept as a value outlives the call that made it, losing only its parent
+        let mut scope = run(
+            "f = fn (\n [inner] : ( a = 1 )\n ret = [inner]\n)\nf ! ()\nv = ret\nf ! ()\nx = v.zzz",
+        )
+        .unwrap();
+        assert_eq!(value(&mut scope, &string_list!("x")), Value::Null);
+        assert_eq!(value(&mut scope, &string_list!("v", "a")), Value::Int(1));
+
+        // A scope is never stored in itself or below, as it would then own itself
+        for source in [
+            "[a] : (me = [a])",
+            "[a] : (me = ([a]))",
+            "[a] : ([b] : (up = {a ~ [a]}))",
+            "[a] : ()\na.me = [a]",
+            "x = [a]\n[a] : (y ~ x)",
+            "[a] : (l = (1))\na.l.0 = [a]",
+            "[a] : ()\n[b] : (x = [a])\n[a] : (y = [b])",
+            "[a] : ()\n[b] : (x = ([a]))\na.y = {b ~ [b]}",
+            "[a] : (l = (1))\n[b] : (x = [a])\na.l.0 = [b]",
+        ] {
+            let e = run(source).unwrap_err().to_string();
+            assert!(
+                e.contains("holds a scope it is set in"),
+                "{}: {}",
+                source,
+                e
+            );
+        }
+        let e = run("l = (1)\nm = {x ~ l}\nl.0 = m")
+            .unwrap_err()
+            .to_string();
+        assert!(e.contains("holds the value it is set in"), "{}", e);
+        let mut scope = run("[a] : ()\nx = [a]\n[a] : (b = 1)\n[c] : (y = [a])").unwrap();
+        assert_eq!(value(&mut scope, &string_list!("x", "b")), Value::Int(1));
+    }
+
+    #[test]
+    fn test_bytes() {
+        let mut scope = run(r#"
+            raw = b"ab\x00\n"
+            key = hex"DE AD be ef"
+            blob = b64"aGVsbG8="
+            short = b64"aGk"
+            size!(L ~ key)
+            len ~ ret
+            slice!(L ~ key I = 1 E = 3)
+            mid ~ ret
+            slice!(L ~ key I = 2)
+            tail ~ ret
+            concat!(A ~ blob B = b"!")
+            joined ~ ret
+            to_str!(V ~ joined)
+            text ~ ret
+            to_base64!(V ~ key)
+            encoded ~ ret
+            from_base64!(V ~ encoded)
+            decoded ~ ret
+            to_hex!(V ~ short)
+            hex ~ ret
+            from_hex!(V = "0aff")
+            unhexed ~ ret
+        "#)
+        .unwrap();
+        let mut get = |name: &str| value(&mut scope, &string_list!(name));
+        assert_eq!(get("raw"), Value::Bytes(vec![b'a', b'b', 0, b'\n']));
+        assert_eq!(get("key").to_string(), r#"hex"deadbeef""#);
+        assert_eq!(get("blob"), Value::Bytes(b"hello".to_vec()));
+        assert_eq!(get("len"), Value::Int(4));
+        assert_eq!(get("mid"), Value::Bytes(vec![0xad, 0xbe]));
+        assert_eq!(get("tail"), Value::Bytes(vec![0xbe, 0xef]));
+        assert_eq!(get("text"), Value::Str("hello!".to_string()));
+        assert_eq!(get("encoded"), Value::Str("3q2+7w==".to_string()));
+        assert_eq!(get("decoded"), get("key"));
+        assert_eq!(get("hex"), Value::Str("6869".to_string()));
+        assert_eq!(get("unhexed"), Value::Bytes(vec![0x0a, 0xff]));
+
+        for (bytes, base64) in [("", ""), ("f", "Zg=="), ("fo", "Zm8="), ("foo", "Zm9v")] {
+            assert_eq!(encode_base64(bytes.as_bytes()), base64);
+            assert_eq!(decode_base64(base64).unwrap(), bytes.as_bytes());
+        }
+
+        // Bytes are kept by the cache
+        let source = r#"k = hex"00ff""#;
+        let token = Parser::new(source).parse().unwrap();
+        let syntax = Syntax::default();
+        let bytes = write_cache(&token, source, &syntax).unwrap();
+        let cached = read_cache(&bytes, source, &syntax, 0).unwrap();
+        assert_eq!(cached.to_string(), token.to_string());
+
+        assert!(Parser::new(r#"k = hex"abc""#).parse().is_err());
+        assert!(Parser::new(r#"k = hex"xy""#).parse().is_err());
+        assert!(Parser::new(r#"k = b64"a===""#).parse().is_err());
+        assert!(Parser::new(r#"k = b"\x4""#).parse().is_err());
+        assert!(Parser::new(r#"k = b"abc"#).parse().is_err());
+        assert!(run(r#"k = hex"ff" to_str!(V ~ k)"#).is_err());
+        assert!(run(r#"k = hex"ff" slice!(L ~ k I = 0 E = 2)"#).is_err());
+    }
+
+    #[test]
+    fn test_math() {
+        let calc = |call: &str| {
+            let mut scope = run(call)?;
+            Ok::<_, RuntimeError>(value(&mut scope, &string_list!("ret")))
+        };
+        for (call, expected) in [
+            ("add!(A = 1 B = 2)", Value::Int(3)),
+            ("add!(A = 1 B = 2.5)", Value::Float(3.5)),
+            ("sub!(A = 1.5 B = 2)", Value::Float(-0.5)),
+            ("mul!(A = 4294967296 B = 2)", Value::Int(8589934592)),
+            ("div!(A = 7 B = 2)", Value::Int(3)),
+            ("div!(A = 7 B = 2.0)", Value::Float(3.5)),
+            ("mod!(A = -7 B = 3)", Value::Int(-1)),
+            ("mod!(A = 7.5 B = 2)", Value::Float(1.5)),
+            ("pow!(A = 3 B = 4)", Value::Int(81)),
+            ("pow!(A = 2 B = -1)", Value::Float(0.5)),
+            ("pow!(A = 4 B = 0.5)", Value::Float(2.0)),
+            ("abs!(V = -3)", Value::Int(3)),
+            ("abs!(V = -3.5)", Value::Float(3.5)),
+            ("neg!(V = 3)", Value::Int(-3)),
+            ("min!(A = 2 B = 1.5)", Value::Float(1.5)),
+            ("max!(A = 2 B = 1.5)", Value::Int(2)),
+            ("min!(A = \"a\" B = \"b\")", Value::Str("a".to_string())),
+            ("floor!(V = -1.5)", Value::Int(-2)),
+            ("ceil!(V = 1.2)", Value::Int(2)),
+            ("round!(V = 2.5)", Value::Int(3)),
+            ("round!(V = 7)", Value::Int(7)),
+            ("eq!(A = 1 B = 1.0)", Value::Bool(true)),
+            ("lt!(A = 2 B = 1.5)", Value::Bool(false)),
+            ("ge!(A = 2 B = 2.0)", Value::Bool(true)),
+        ] {
+            assert_eq!(calc(call).unwrap(), expected, "{}", call);
+        }
+
+        for (call, message) in [
+            ("add!(A = 9223372036854775807 B = 1)", "Integer overflow"),
+            ("mul!(A = 4294967296 B = 4294967296)", "Integer overflow"),
+            ("sub!(A = -9223372036854775807 B = 2)", "Integer overflow"),
+            ("div!(A = 1 B = 0)", "Division by zero"),
+            ("mod!(A = 1 B = 0)", "Division by zero"),
+            ("pow!(A = 2 B = 64)", "Integer overflow"),
+            ("neg!(V = -9223372036854775808)", "Integer overflow"),
+            ("abs!(V = -9223372036854775808)", "Integer overflow"),
+            ("neg!(V = \"1\")", "Expected a number"),
+            ("add!(A = 1 B = \"2\")", "Expected two numbers"),
+            ("round!(V = 1e19)", "within the range"),
+            ("round!(V = nan)", "within the range"),
+            ("min!(A = 1 B = \"a\")", "Cannot compare"),
+        ] {
+            let e = calc(call).unwrap_err().to_string();
+            assert!(e.contains(message), "{}: {}", call, e);
+        }
+    }
+
+    #[test]
+    fn test_units() {
+        use std::time::Duration;
+
+        let mut scope = run(r#"
+            timeout = 30s
+            retry = 250ms
+            window = 1.5h
+            buffer = 64KiB
+            disk = 1.5GB
+            add!(A = timeout B = retry)
+            total ~ ret
+            mul!(A = 3 B = buffer)
+            tripled ~ ret
+            div!(A = window B = timeout)
+            ratio ~ ret
+            lt!(A = retry B = timeout)
+            shorter ~ ret
+            max!(A = 1MB B = 1MiB)
+            larger ~ ret
+        "#)
+        .unwrap();
+        let mut get = |name: &str| value(&mut scope, &string_list!(name));
+        assert_eq!(get("timeout").as_duration(), Some(Duration::from_secs(30)));
+        assert_eq!(get("window").as_duration(), Some(Duration::from_secs(5400)));
+        assert_eq!(get("buffer").as_size(), Some(65536));
+        assert_eq!(get("disk").as_size(), Some(1_500_000_000));
+        assert_eq!(get("timeout").as_size(), None);
+        assert_eq!(get("total").to_string(), "30250ms");
+        assert_eq!(get("tripled").to_string(), "192KiB");
+        assert_eq!(get("ratio"), Value::Float(180.0));
+        assert_eq!(get("shorter"), Value::Bool(true));
+        assert_eq!(get("larger").to_string(), "1MiB");
+        assert_eq!(get("window").to_string(), "90m");
+        assert_eq!(get("disk").to_string(), "1500MB");
+
+        for call in [
+            "sub!(A = 1s B = 2s)",
+            "add!(A = 1s B = 1B)",
+            "add!(A = 1s B = 1)",
+            "mul!(A = 1s B = -1)",
+            "div!(A = 1s B = 0)",
+            "mul!(A = 18446744073709551615B B = 2)",
+        ] {
+            assert!(run(call).is_err(), "{}", call);
+        }
+        // Durations and sizes are only ordered against their own kind
+        for call in [
+            "lt!(A = 1s B = 1B)",
+            "le!(A = 1s B = 1)",
+            "gt!(A = 1KiB B = 1.5)",
+            "ge!(A = 1B B = 1ms)",
+            "lt!(A = 1 B = \"a\")",
+        ] {
+            let e = run(call).unwrap_err().to_string();
+            assert!(e.contains("Cannot compare"), "{}: {}", call, e);
+        }
+        for str in ["1sec", "-5s", "0.5B", "1.5ns", "999999999999999d", "1s5"] {
+            assert!(
+                Parser::new(format!("a = {}", str)).parse().is_err(),
+                "{}",
+                str
+            );
+        }
+    }
+
+    fn random_str(rng: &mut StdRng) -> String {
+        const CHARS: &[char] = &[
+            'a', 'Z', '0', '_', ' ', '.', '-', '"', '\'', '`', '\\', '\n', '\t', '\r', '\0',
+            '\u{1}', '\u{7f}', '#', '(', ')', '{', '}', '[', ']', '=', 'é', '名', '😀',
+        ];
+        (0..rng.gen_range(0..8))
+            .map(|_| CHARS[rng.gen_range(0..CHARS.len())])
+            .collect()
+    }
+
+    fn random_name(rng: &mut StdRng) -> String {
+        const NAMES: &[&str] = &["a", "port", "true", "fn", "größe", "0", "a.b", "x y"];
+        if rng.gen_bool(0.5) {
+            NAMES[rng.gen_range(0..NAMES.len())].to_string()
+        } else {
+            format!("n{}", random_str(rng))
+        }
+    }
+
+    fn random_scope(rng: &mut StdRng, depth: u32) -> Scope {
+        let mut scope = Scope::new();
+        for _ in 0..rng.gen_range(0..4) {
+            let value = rc_cell(random_value(rng, depth));
+            scope.set_value(&[random_name(rng)], value).unwrap();
+        }
+        if depth > 0 && rng.gen_bool(0.3) {
+            let child = rc_cell(random_scope(rng, depth - 1));
+            scope.set_scope(&[random_name(rng)], child).unwrap();
+        }
+        scope
+    }
+
+    fn random_value(rng: &mut StdRng, depth: u32) -> Value {
+        let kinds = if depth == 0 { 9 } else { 12 };
+        match rng.gen_range(0..kinds) {
+            0 => Value::Int(rng.gen()),
+            1 => Value::Float(f64::from_bits(rng.gen())),
+            2 => Value::Float(rng.gen_range(-1e3..1e3)),
+            3 => Value::Bool(rng.gen()),
+            4 => Value::Str(random_str(rng)),
+            5 => Value::Bytes((0..rng.gen_range(0..6)).map(|_| rng.gen()).collect()),
+            6 => Value::Duration(std::time::Duration::new(
+                rng.gen(),
+                rng.gen_range(0..1_000_000_000),
+            )),
+            7 => Value::Size(rng.gen::<u64>() >> rng.gen_range(0..64)),
+            8 => Value::Null,
+            9 => Value::List(
+                (0..rng.gen_range(0..4))
+                    .map(|_| rc_cell(random_value(rng, depth - 1)))
+                    .collect(),
+            ),
+            10 => {
+                let mut map = Map::new();
+                for _ in 0..rng.gen_range(0..4) {
+                    map.insert(random_str(rng), rc_cell(random_value(rng, depth - 1)));
+                }
+                Value::Map(map)
+            }
+            _ => Value::Scope(rc_cell(random_scope(rng, depth - 1))),
+        }
+    }
+
+    #[test]
+    fn test_literal() {
+        let mut rng = StdRng::seed_from_u64(21);
+        for _ in 0..2000 {
+            let expected = match random_value(&mut rng, 3) {
+                // NaN is never equal to itself
+                Value::Float(f) if f.is_nan() => continue,
+                expected => expected,
+            };
+            let literal = expected.to_ipml_literal().unwrap();
+            let mut scope =
+                run(&format!("v = {}", literal)).unwrap_or_else(|e| panic!("{}\n{}", literal, e));
+            assert_eq!(
+                value(&mut scope, &string_list!("v")),
+                expected,
+                "{}",
+                literal
+            );
+        }
+
+        for (value, literal) in [
+            (Value::Float(8.0), "8.0"),
+            (Value::Float(f64::NEG_INFINITY), "-inf"),
+            (Value::Float(f64::NAN), "nan"),
+            (Value::Str("a \"b\"\n".to_string()), r#""a \"b\"\n""#),
+            (Value::Bytes(vec![1, 2]), r#"hex"0102""#),
+        ] {
+            assert_eq!(value.to_ipml_literal().unwrap(), literal);
+        }
+        let mut scope = run("l = (1 2.0 \"3\" {`true` = null} indep(a = 1))").unwrap();
+        assert_eq!(
+            value(&mut scope, &string_list!("l"))
+                .to_ipml_literal()
+                .unwrap(),
+            "(1 2.0 \"3\" {`true` = null} indep(a = 1))"
+        );
+        let e = value(&mut scope, &string_list!("print")).to_ipml_literal();
+        assert!(e.is_err());
+    }
+
+    #[test]
+    fn test_write_scope() {
+        let feed = |source: &str| {
+            let mut scope = Scope::new();
+            scope.feed(&Parser::new(source).parse().unwrap()).unwrap();
+            scope
+        };
+        let mut scope = run(r#"
+            port = 8080
+            name = "web \"1\""
+            [server.tls] : (cert = b"\x00" `max-age` = 30d)
+            hosts = ("a" "b")
+            push_back!(L ~ hosts V = "c")
+        "#)
+        .unwrap();
+        scope.query_scope(&string_list!("server")).unwrap();
+        let source = scope.to_ipml_string().unwrap();
+        assert_eq!(
+            source,
+            r#"hosts = ("a" "b" "c")
+name  = "web \"1\""
+port  = 8080
+[server] = (
+    [tls] = (
+        cert      = hex"00"
+        `max-age` = 30d
+    )
+)
+"#
+        );
+        let mut written = Vec::new();
+        scope.write_ipml(&mut written).unwrap();
+        assert_eq!(String::from_utf8(written).unwrap(), source);
+
+        let mut rng = StdRng::seed_from_u64(22);
+        for _ in 0..500 {
+            let scope = random_scope(&mut rng, 3);
+            let source = scope.to_ipml_string().unwrap();
+            assert!(feed(&source) == scope, "{}", source);
+        }
+    }
+}